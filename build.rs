@@ -1,11 +1,16 @@
 // build.rs
 
-use std::env;
-use std::path::PathBuf;
+fn main() {
+    // The libtinyfseq bindings are only needed by the optional FFI parser backend.
+    #[cfg(feature = "tinyfseq")]
+    generate_tinyfseq_bindings();
+}
 
-extern crate bindgen;
+#[cfg(feature = "tinyfseq")]
+fn generate_tinyfseq_bindings() {
+    use std::env;
+    use std::path::PathBuf;
 
-fn main() {
     // 1. Tell cargo to link the compiled libtinyfseq library.
 
     // 2. Locate the header file using the Nix environment variable.
@@ -19,10 +19,18 @@
         
             settings = {
               host = lib.mkOption {
-                type = lib.types.str;
-                description = "IP address of the WLED controller (corresponds to --host).";
+                type = lib.types.nullOr lib.types.str;
+                description = "IP address of the WLED controller; required unless controllers is set (corresponds to --host).";
+                default = null;
                 example = "127.0.0.1";
               };
+
+              controllers = lib.mkOption {
+                type = lib.types.nullOr lib.types.path;
+                description = "JSON map of controllers, each receiving its own channel range, instead of host and port (corresponds to --controllers).";
+                default = null;
+                example = "/home/user/sequences/controllers.json";
+              };
         
               port = lib.mkOption {
                 type = lib.types.ints.u16;
@@ -30,17 +38,104 @@
                 default = 4048;
               };
         
+              probe = lib.mkOption {
+                type = lib.types.nullOr (lib.types.enum [ "icmp" "http" "tcp" "ddp" ]);
+                description = "How the monitor checks that host is up; icmp when unset (corresponds to --probe).";
+                default = null;
+              };
+
+              monitor-interval = lib.mkOption {
+                type = lib.types.str;
+                description = "How often to check the controllers while not streaming (corresponds to --monitor-interval).";
+                default = "30s";
+              };
+
+              streaming-monitor-interval = lib.mkOption {
+                type = lib.types.str;
+                description = "How often to check the controllers while streaming (corresponds to --streaming-monitor-interval).";
+                default = "5s";
+              };
+
+              failure-threshold = lib.mkOption {
+                type = lib.types.ints.positive;
+                description = "Consecutive failed checks before a controller counts as down (corresponds to --failure-threshold).";
+                default = 3;
+              };
+
+              success-threshold = lib.mkOption {
+                type = lib.types.ints.positive;
+                description = "Consecutive successful checks before a controller that went down counts as up again (corresponds to --success-threshold).";
+                default = 2;
+              };
+
+              max-backoff = lib.mkOption {
+                type = lib.types.str;
+                description = "Longest interval between checks of a down controller, which doubles after each failed check up to this. A controller that comes back is reported up at worst this long plus success-threshold - 1 intervals later (corresponds to --max-backoff).";
+                default = "1m";
+              };
+
+              on-claimed = lib.mkOption {
+                type = lib.types.enum [ "ignore" "yield" "stop" ];
+                description = "What to do when a WLED controller is taken over from its app or by another realtime source (corresponds to --on-claimed).";
+                default = "yield";
+              };
+
+              override-hold = lib.mkOption {
+                type = lib.types.str;
+                description = "How long a controller stays claimed after a live override that lasts until realtime data stops (lor 1) is cleared (corresponds to --override-hold).";
+                default = "5m";
+              };
+
               file = lib.mkOption {
-                type = lib.types.path;
-                description = "Path to the FSEQ sequence file. (Corresponds to --file).";
+                type = lib.types.nullOr lib.types.path;
+                description = "Path to the FSEQ sequence file; required unless playlist or schedule is set. (Corresponds to --file).";
+                default = null;
                 example = "/home/user/sequences/my_show.fseq";
               };
+
+              playlist = lib.mkOption {
+                type = lib.types.nullOr lib.types.path;
+                description = "M3U or JSON playlist to play instead of a single file (corresponds to --playlist).";
+                default = null;
+                example = "/home/user/sequences/show.m3u";
+              };
+
+              schedule = lib.mkOption {
+                type = lib.types.nullOr lib.types.path;
+                description = "JSON schedule of windows to play in; without one the player streams whenever the controller is up (corresponds to --schedule).";
+                default = null;
+                example = "/home/user/sequences/schedule.json";
+              };
+
+              shuffle = lib.mkOption {
+                type = lib.types.bool;
+                description = "Play the playlist in a new random order on every pass (corresponds to --shuffle).";
+                default = false;
+              };
         
               loop-enabled = lib.mkOption {
                 type = lib.types.bool;
-                description = "Enable continuous looping of the FSEQ sequence (corresponds to --loop-enabled).";
+                description = "Enable continuous looping of the sequence or playlist (corresponds to --loop-enabled).";
                 default = true;
               };
+
+              block-cache-mb = lib.mkOption {
+                type = lib.types.ints.positive;
+                description = "Memory budget in MiB for cached decompressed blocks of compressed sequences (corresponds to --block-cache-mb).";
+                default = 64;
+              };
+
+              load-mode = lib.mkOption {
+                type = lib.types.enum [ "auto" "memory" "mmap" "stream" ];
+                description = "How to load the sequence: read into memory, memory-map, or stream frames from disk (corresponds to --load-mode).";
+                default = "auto";
+              };
+
+              resume = lib.mkOption {
+                type = lib.types.enum [ "restart" "last-frame" "wall-clock" ];
+                description = "Where playback continues after the controller goes offline and returns (corresponds to --resume).";
+                default = "restart";
+              };
             };
           };
           config = lib.mkIf cfg.enable {
@@ -0,0 +1,251 @@
+// src/block_cache.rs
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use crate::fseq_error::FseqError;
+
+/// Default memory budget for decompressed blocks (64 MiB).
+pub const DEFAULT_BLOCK_CACHE_BUDGET: usize = 64 * 1024 * 1024;
+
+// ----------------------------------------------------------------------
+// BlockCache Struct
+// ----------------------------------------------------------------------
+/// LRU cache of decompressed compression blocks, bounded by a byte budget.
+///
+/// The most recently inserted block is always kept, even if it alone exceeds the budget,
+/// so sequential playback never decompresses the same block twice in a row. Prefetched blocks
+/// never evict the most recently used block, which is the one playback is reading.
+pub struct BlockCache {
+    budget_bytes: usize,
+    used_bytes: usize,
+    /// Cached blocks ordered from most to least recently used.
+    entries: VecDeque<(usize, Arc<Vec<u8>>)>,
+    /// Blocks currently being decompressed by the prefetch worker.
+    pending: HashSet<usize>,
+    /// Blocks whose data failed to decode, so they are not decoded again on every frame.
+    failures: HashMap<usize, FseqError>,
+}
+
+impl BlockCache {
+    pub fn new(budget_bytes: usize) -> Self {
+        BlockCache {
+            budget_bytes,
+            used_bytes: 0,
+            entries: VecDeque::new(),
+            pending: HashSet::new(),
+            failures: HashMap::new(),
+        }
+    }
+
+    /// Returns the cached block and marks it as most recently used.
+    pub fn get(&mut self, block_index: usize) -> Option<Arc<Vec<u8>>> {
+        let position = self.entries.iter().position(|(index, _)| *index == block_index)?;
+        let entry = self.entries.remove(position)?;
+        let data = entry.1.clone();
+        self.entries.push_front(entry);
+        Some(data)
+    }
+
+    pub fn contains(&self, block_index: usize) -> bool {
+        self.entries.iter().any(|(index, _)| *index == block_index)
+    }
+
+    /// Inserts a decompressed block, evicting least recently used blocks until the budget is met.
+    pub fn insert(&mut self, block_index: usize, data: Arc<Vec<u8>>) {
+        self.pending.remove(&block_index);
+
+        if let Some(position) = self.entries.iter().position(|(index, _)| *index == block_index)
+            && let Some((_, old)) = self.entries.remove(position)
+        {
+            self.used_bytes -= old.len();
+        }
+
+        self.used_bytes += data.len();
+        self.entries.push_front((block_index, data));
+        self.evict();
+    }
+
+    /// Inserts a block decompressed ahead of playback. It only evicts blocks other than the
+    /// most recently used one, and is dropped if it does not fit beside that block.
+    /// Returns whether the block was kept.
+    pub fn insert_prefetched(&mut self, block_index: usize, data: Arc<Vec<u8>>) -> bool {
+        self.pending.remove(&block_index);
+        if self.contains(block_index) {
+            return true;
+        }
+
+        let in_use_bytes = self.entries.front().map_or(0, |(_, in_use)| in_use.len());
+        if in_use_bytes + data.len() > self.budget_bytes {
+            return false;
+        }
+
+        // Behind the block being read, which stays the most recently used.
+        self.used_bytes += data.len();
+        self.entries.insert(self.entries.len().min(1), (block_index, data));
+        self.evict();
+        true
+    }
+
+    /// Records that a block failed to decode. Only failures a retry cannot fix are kept.
+    pub fn insert_failure(&mut self, block_index: usize, error: &FseqError) {
+        self.pending.remove(&block_index);
+        if let Some(error) = error.cached_copy() {
+            self.failures.insert(block_index, error);
+        }
+    }
+
+    /// Returns a copy of the error a block failed with earlier, if any.
+    pub fn failure(&self, block_index: usize) -> Option<FseqError> {
+        self.failures.get(&block_index).and_then(FseqError::cached_copy)
+    }
+
+    /// Marks a block as queued for prefetch. Returns false if it is already cached, failed or queued.
+    pub fn mark_pending(&mut self, block_index: usize) -> bool {
+        if self.contains(block_index) || self.failures.contains_key(&block_index) {
+            return false;
+        }
+        self.pending.insert(block_index)
+    }
+
+    pub fn is_pending(&self, block_index: usize) -> bool {
+        self.pending.contains(&block_index)
+    }
+
+    pub fn clear_pending(&mut self, block_index: usize) {
+        self.pending.remove(&block_index);
+    }
+
+    pub fn set_budget(&mut self, budget_bytes: usize) {
+        self.budget_bytes = budget_bytes;
+        self.evict();
+    }
+
+    pub fn budget(&self) -> usize {
+        self.budget_bytes
+    }
+
+    pub fn used_bytes(&self) -> usize {
+        self.used_bytes
+    }
+
+    fn evict(&mut self) {
+        while self.used_bytes > self.budget_bytes && self.entries.len() > 1 {
+            if let Some((_, evicted)) = self.entries.pop_back() {
+                self.used_bytes -= evicted.len();
+            }
+        }
+    }
+}
+
+// ----------------------------------------------------------------------
+// SharedBlockCache Struct
+// ----------------------------------------------------------------------
+/// A `BlockCache` shared with the prefetch worker. Readers of a block the worker is still
+/// decompressing wait for it instead of decompressing it a second time.
+pub struct SharedBlockCache {
+    cache: Mutex<BlockCache>,
+    /// Signalled whenever a pending block is inserted, fails or is dropped.
+    resolved: Condvar,
+}
+
+impl SharedBlockCache {
+    pub fn new(budget_bytes: usize) -> Self {
+        SharedBlockCache { cache: Mutex::new(BlockCache::new(budget_bytes)), resolved: Condvar::new() }
+    }
+
+    pub fn lock(&self) -> MutexGuard<'_, BlockCache> {
+        self.cache.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Waits until `block_index` is no longer pending.
+    pub fn wait_for<'a>(&self, cache: MutexGuard<'a, BlockCache>, block_index: usize) -> MutexGuard<'a, BlockCache> {
+        self.resolved
+            .wait_while(cache, |cache| cache.is_pending(block_index))
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Wakes the readers waiting for pending blocks.
+    pub fn notify_resolved(&self) {
+        self.resolved.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn block(len: usize) -> Arc<Vec<u8>> {
+        Arc::new(vec![0u8; len])
+    }
+
+    #[test]
+    fn evicts_least_recently_used_blocks_over_budget() {
+        let mut cache = BlockCache::new(300);
+        cache.insert(0, block(100));
+        cache.insert(1, block(100));
+        cache.insert(2, block(100));
+        assert_eq!(cache.used_bytes(), 300);
+
+        // Touching block 0 makes block 1 the least recently used.
+        assert!(cache.get(0).is_some());
+        cache.insert(3, block(100));
+        assert!(!cache.contains(1));
+        assert!(cache.contains(0) && cache.contains(2) && cache.contains(3));
+        assert_eq!(cache.used_bytes(), 300);
+
+        // Re-inserting a block replaces its bytes instead of counting them twice.
+        cache.insert(3, block(50));
+        assert_eq!(cache.used_bytes(), 250);
+
+        cache.set_budget(120);
+        assert!(cache.contains(3) && !cache.contains(0) && !cache.contains(2));
+        assert_eq!(cache.used_bytes(), 50);
+    }
+
+    #[test]
+    fn keeps_the_most_recent_block_over_budget() {
+        let mut cache = BlockCache::new(100);
+        cache.insert(0, block(60));
+        cache.insert(1, block(250));
+
+        assert!(!cache.contains(0));
+        assert!(cache.get(1).is_some());
+        assert_eq!(cache.used_bytes(), 250);
+    }
+
+    #[test]
+    fn prefetches_never_evict_the_block_in_use() {
+        // The budget fits exactly one block.
+        let mut cache = BlockCache::new(100);
+        cache.insert(0, block(100));
+        assert!(cache.mark_pending(1));
+        assert!(!cache.insert_prefetched(1, block(100)));
+        assert!(cache.contains(0) && !cache.contains(1));
+        assert!(!cache.is_pending(1));
+
+        // With room for two, the prefetched block stays behind the one being read.
+        cache.set_budget(200);
+        cache.insert(2, block(100));
+        assert!(cache.get(0).is_some());
+        assert!(cache.insert_prefetched(1, block(100)));
+        assert!(cache.contains(0) && cache.contains(1) && !cache.contains(2));
+        // Once playback moves on to it, the previous block is the one evicted.
+        assert!(cache.get(1).is_some());
+        cache.insert(3, block(100));
+        assert!(!cache.contains(0) && cache.contains(1));
+    }
+
+    #[test]
+    fn deduplicates_pending_blocks() {
+        let mut cache = BlockCache::new(1000);
+        assert!(cache.mark_pending(4));
+        assert!(!cache.mark_pending(4));
+
+        // Inserting the block clears its pending mark, and cached blocks are never queued.
+        cache.insert(4, block(10));
+        assert!(!cache.mark_pending(4));
+
+        assert!(cache.mark_pending(5));
+        cache.clear_pending(5);
+        assert!(cache.mark_pending(5));
+    }
+}
@@ -1,23 +1,201 @@
 // src/cli.rs
-use clap::Parser;
+use clap::{ArgAction, Args, Parser, Subcommand};
+use crate::fseq::CompressionType;
+use crate::fseq_storage::LoadMode;
+use crate::fseq_writer::{FseqVersion, DEFAULT_FRAMES_PER_BLOCK};
+use crate::output::DDP_PORT;
+use crate::monitor::ClaimPolicy;
+use crate::playback::ResumePolicy;
+use crate::playlist::parse_gap;
+use crate::probe::ProbeKind;
+use std::time::Duration;
 
 /// FSEQ DDP Player
+///
+/// Without a subcommand the player streams `--file` to `--host`.
 #[derive(Parser, Debug)]
 #[command(author, version, about = "A DDP player that streams FSEQ data to a WLED controller, pausing automatically when the controller is offline.", long_about = None)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+// `-h` is taken by `--host`, so help is only available as `--help`. The setting propagates to
+// subcommands, which declare their own `-h`/`--help`.
+#[command(disable_help_flag = true)]
 pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub play: PlayArgs,
+
+    /// Print help
+    #[arg(long, action = ArgAction::Help)]
+    help: Option<bool>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Re-encode an FSEQ file with a different compression, block size, layout or frame range
+    Convert(ConvertArgs),
+    /// Print the header, variable headers, block table and sparse ranges of an FSEQ file
+    Info(InspectArgs),
+    /// Decode every frame of an FSEQ file and report problems; exits non-zero if it is invalid
+    Validate(InspectArgs),
+}
+
+/// Options for streaming a sequence to a WLED controller.
+///
+/// `host` and `file` are required unless a subcommand is given, so they are optional here.
+#[derive(Args, Debug)]
+pub struct PlayArgs {
     /// IP address of the WLED controller (e.g., 192.168.1.50)
-    #[arg(short, long)]
-    pub host: String,
+    #[arg(short, long, required_unless_present = "controllers")]
+    pub host: Option<String>,
 
     /// UDP port for the Distributed Display Protocol (DDP)
-    #[arg(short, long, default_value_t = 4048)]
+    #[arg(short, long, default_value_t = DDP_PORT)]
     pub port: u16,
 
-    /// Path to the FSEQ sequence file
-    #[arg(short, long)]
-    pub file: String,
-    
-    /// Enable continuous looping of the FSEQ sequence
+    /// JSON map of controllers, each receiving its own channel range of the sequence,
+    /// instead of sending everything to --host and --port
+    #[arg(long, conflicts_with = "host")]
+    pub controllers: Option<String>,
+
+    /// How the monitor checks that --host is up: ICMP ping, WLED's /json/info over HTTP,
+    /// a TCP connect to port 80, or a DDP status query
+    #[arg(long, value_enum, default_value_t = ProbeKind::Icmp, conflicts_with = "controllers")]
+    pub probe: ProbeKind,
+
+    /// How often to check the controllers while not streaming (e.g. 30s, 1m)
+    #[arg(long, value_parser = parse_interval, default_value = "30s")]
+    pub monitor_interval: Duration,
+
+    /// How often to check the controllers while streaming
+    #[arg(long, value_parser = parse_interval, default_value = "5s")]
+    pub streaming_monitor_interval: Duration,
+
+    /// Consecutive failed checks before a controller counts as down
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..), default_value_t = 3)]
+    pub failure_threshold: u32,
+
+    /// Consecutive successful checks before a controller that went down counts as up again
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..), default_value_t = 2)]
+    pub success_threshold: u32,
+
+    /// Longest interval between checks of a down controller; the interval doubles after
+    /// every failed check up to this. A controller that comes back is reported up within this
+    /// plus (success threshold - 1) intervals
+    #[arg(long, value_parser = parse_interval, default_value = "1m")]
+    pub max_backoff: Duration,
+
+    /// What to do when a WLED controller is taken over from its app or by another realtime
+    /// source: keep sending (ignore), stop sending to that controller until it is released
+    /// (yield), or stop the whole show (stop)
+    #[arg(long, value_enum, default_value_t = ClaimPolicy::Yield)]
+    pub on_claimed: ClaimPolicy,
+
+    /// How long a controller stays claimed after a live override set in WLED until realtime
+    /// data stops (lor 1) is cleared, which happens as soon as the player yields it
+    #[arg(long, value_parser = parse_interval, default_value = "5m")]
+    pub override_hold: Duration,
+
+    /// Path to the FSEQ sequence file; give several to play them in order
+    #[arg(short, long, num_args = 1.., required_unless_present_any = ["playlist", "schedule"])]
+    pub file: Vec<String>,
+
+    /// M3U or JSON playlist to play instead of --file
+    #[arg(long, conflicts_with = "file")]
+    pub playlist: Option<String>,
+
+    /// Play the sequences in a new random order on every pass through the playlist
+    #[arg(long)]
+    pub shuffle: bool,
+
+    /// Pause with the lights off between the sequences given with --file (e.g. 2s, 500ms)
+    #[arg(long, value_parser = parse_gap, conflicts_with = "playlist")]
+    pub gap: Option<Duration>,
+
+    /// JSON schedule of weekly windows and date ranges to play in, each with an optional
+    /// playlist; without one, the player streams whenever the controller is up
+    #[arg(long)]
+    pub schedule: Option<String>,
+
+    /// Enable continuous looping of the sequence or playlist
     #[arg(long, default_value_t = true)]
     pub loop_enabled: bool,
+
+    /// Memory budget in MiB for cached decompressed blocks of compressed sequences
+    #[arg(long, default_value_t = 64)]
+    pub block_cache_mb: usize,
+
+    /// Disable decompressing the next block on a background thread
+    #[arg(long)]
+    pub no_prefetch: bool,
+
+    /// How to load the sequence: read small files into memory and map large ones (auto),
+    /// read it all into memory, memory-map it, or stream frames from disk
+    #[arg(long, value_enum, default_value_t = LoadMode::Auto)]
+    pub load_mode: LoadMode,
+
+    /// Where to continue after the controller drops out and comes back: from the start
+    /// (restart), from the first unsent frame (last-frame), or from where the sequence
+    /// would be had it kept playing (wall-clock)
+    #[arg(long, value_enum, default_value_t = ResumePolicy::Restart)]
+    pub resume: ResumePolicy,
+}
+
+/// Options for `convert`.
+#[derive(Args, Debug)]
+pub struct ConvertArgs {
+    /// FSEQ file to read (v1 or v2, any compression)
+    pub input: String,
+
+    /// Path of the FSEQ file to write
+    pub output: String,
+
+    /// Output compression [default: the source compression, none for v1]
+    #[arg(short, long, value_enum)]
+    pub compression: Option<CompressionType>,
+
+    /// Frames per compression block; 0 compresses all frames as a single stream
+    #[arg(long, default_value_t = DEFAULT_FRAMES_PER_BLOCK)]
+    pub frames_per_block: u32,
+
+    /// Output layout; v1 is always uncompressed and expands sparse ranges to dense frames
+    #[arg(long = "format", value_name = "FORMAT", value_enum, default_value_t = FseqVersion::V2)]
+    pub version: FseqVersion,
+
+    /// First frame to keep
+    #[arg(long, default_value_t = 0)]
+    pub start_frame: u32,
+
+    /// Frame to stop before [default: the end of the sequence]
+    #[arg(long)]
+    pub end_frame: Option<u32>,
+
+    /// Print help
+    #[arg(short, long, action = ArgAction::Help)]
+    help: Option<bool>,
+}
+
+/// Options for `info` and `validate`.
+#[derive(Args, Debug)]
+pub struct InspectArgs {
+    /// FSEQ file to inspect
+    pub file: String,
+
+    /// Print JSON instead of human-readable text
+    #[arg(long)]
+    pub json: bool,
+
+    /// Print help
+    #[arg(short, long, action = ArgAction::Help)]
+    help: Option<bool>,
+}
+
+/// Parses a monitor interval such as `5s` or `1m`; zero would poll in a busy loop.
+fn parse_interval(value: &str) -> anyhow::Result<Duration> {
+    let interval = parse_gap(value)?;
+    if interval.is_zero() {
+        anyhow::bail!("interval must be greater than zero");
+    }
+    Ok(interval)
 }
@@ -0,0 +1,202 @@
+// src/convert.rs
+use anyhow::{Context, Result, bail};
+use std::io::BufWriter;
+use std::path::Path;
+use crate::fseq::{CompressionType, FseqFile};
+use crate::fseq_storage::LoadMode;
+use crate::fseq_writer::{FseqVersion, FseqWriter, PendingFile};
+
+#[derive(Clone, Debug)]
+pub struct ConvertConfig {
+    pub input_path: String,
+    pub output_path: String,
+    /// Output compression; `None` keeps the source compression (v2) or writes uncompressed (v1).
+    pub compression: Option<CompressionType>,
+    pub frames_per_block: u32,
+    pub version: FseqVersion,
+    /// First frame to keep.
+    pub start_frame: u32,
+    /// Frame to stop before; `None` keeps every frame up to the end of the sequence.
+    pub end_frame: Option<u32>,
+}
+
+// ----------------------------------------------------------------------
+// FSEQ Conversion
+// ----------------------------------------------------------------------
+/// Re-encodes an FSEQ file with a new compression, block size, layout or frame range.
+///
+/// Step time, sequence UID and variable headers are carried over. Sparse files keep their
+/// channel ranges in v2 output and are expanded to dense frames for v1 output.
+///
+/// Frames are written block by block as they are decoded, to a temporary file that replaces
+/// the output at the end, so the output may be the input file itself.
+pub fn convert_sequence(config: &ConvertConfig) -> Result<()> {
+    let fseq_file = FseqFile::open(&config.input_path, LoadMode::Auto)?;
+
+    let frame_count = fseq_file.get_frame_count();
+    let end_frame = config.end_frame.unwrap_or(frame_count);
+    if end_frame > frame_count {
+        bail!("End frame {} is beyond the sequence's {} frames.", end_frame, frame_count);
+    }
+    if config.start_frame >= end_frame {
+        bail!("Frame range {}..{} is empty.", config.start_frame, end_frame);
+    }
+
+    let compression_type = match (config.compression, config.version) {
+        (Some(compression_type), _) => compression_type,
+        (None, FseqVersion::V1) => CompressionType::None,
+        (None, FseqVersion::V2) => fseq_file.header.compression_type,
+    };
+
+    // v1 has no sparse ranges, so each range is placed at its absolute channel in a dense frame.
+    let expand_sparse = fseq_file.is_sparse() && config.version == FseqVersion::V1;
+    let output_channels = if expand_sparse {
+        fseq_file.get_channel_ranges().iter()
+            .map(|range| range.start_channel + range.channel_count)
+            .max()
+            .unwrap_or(0)
+    } else {
+        fseq_file.get_channel_count()
+    };
+
+    let mut writer = FseqWriter::new(output_channels, fseq_file.header.step_time_ms)
+        .with_version(config.version)
+        .with_compression(compression_type)
+        .with_frames_per_block(config.frames_per_block)
+        .with_sequence_uid(fseq_file.header.sequence_uid);
+    if !expand_sparse {
+        writer = writer.with_channel_ranges(fseq_file.get_channel_ranges().to_vec());
+    }
+    for variable_header in fseq_file.variable_headers() {
+        writer = writer.with_variable_header(variable_header.clone());
+    }
+
+    let (pending_file, file) = PendingFile::create(Path::new(&config.output_path))?;
+    let mut stream = writer.stream_to(BufWriter::new(file), end_frame - config.start_frame)?;
+
+    let mut frame_data = vec![0u8; fseq_file.frame_size()];
+    let mut dense_frame = vec![0u8; if expand_sparse { output_channels as usize } else { 0 }];
+
+    for frame_num in config.start_frame..end_frame {
+        fseq_file.read_frame_into(frame_num, &mut frame_data)?;
+
+        if expand_sparse {
+            for segment in fseq_file.frame_segments(&frame_data) {
+                let start = segment.start_channel as usize;
+                dense_frame[start..start + segment.data.len()].copy_from_slice(segment.data);
+            }
+            stream.append_frame(&dense_frame)?;
+        } else {
+            stream.append_frame(&frame_data)?;
+        }
+    }
+
+    let frames_written = stream.frames_written();
+    stream.finish()?.into_inner().context("Failed to flush FSEQ data")?;
+
+    // Release the source (possibly memory-mapped) before the output replaces it.
+    drop(fseq_file);
+    pending_file.commit()?;
+
+    log::info!("Converted {} frames ({}..{}) to {} ({:?}, {} compression).",
+               frames_written, config.start_frame, end_frame, config.output_path, config.version, compression_type);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fseq::ChannelRange;
+    use crate::test_data::{self, TempDir, V2Fixture};
+
+    fn config(input_path: String, output_path: String, version: FseqVersion) -> ConvertConfig {
+        ConvertConfig {
+            input_path,
+            output_path,
+            compression: None,
+            frames_per_block: 32,
+            version,
+            start_frame: 0,
+            end_frame: None,
+        }
+    }
+
+    #[test]
+    fn recompresses_and_trims() {
+        let directory = TempDir::new("convert-trim");
+        let input_path = directory.file("input.fseq");
+        std::fs::write(&input_path, V2Fixture::new(CompressionType::Zlib, 300, 40, 100).build()).unwrap();
+
+        let mut convert_config = config(input_path, directory.file("output.fseq"), FseqVersion::V2);
+        convert_config.compression = Some(CompressionType::Zstd);
+        convert_config.start_frame = 50;
+        convert_config.end_frame = Some(250);
+        convert_sequence(&convert_config).unwrap();
+
+        let output = FseqFile::open(&convert_config.output_path, LoadMode::Memory).unwrap();
+        assert_eq!(output.header.compression_type, CompressionType::Zstd);
+        assert_eq!(output.get_frame_count(), 200);
+        assert_eq!(output.get_compression_blocks().len(), 7);
+        assert_eq!(output.get_media_filename(), Some(test_data::MEDIA_FILENAME));
+        for frame in 0..output.get_frame_count() {
+            assert_eq!(output.get_frame(frame).unwrap(), test_data::frame_data(frame + 50, 40));
+        }
+
+        convert_config.end_frame = Some(301);
+        assert!(convert_sequence(&convert_config).is_err());
+    }
+
+    #[test]
+    fn expands_sparse_ranges_for_v1() {
+        let directory = TempDir::new("convert-v1");
+        let input_path = directory.file("input.fseq");
+        let mut fixture = V2Fixture::new(CompressionType::Zstd, 20, 30, 8);
+        fixture.sparse_ranges = vec![(4, 10), (50, 20)];
+        std::fs::write(&input_path, fixture.build()).unwrap();
+
+        let convert_config = config(input_path.clone(), directory.file("output.fseq"), FseqVersion::V1);
+        convert_sequence(&convert_config).unwrap();
+
+        let input = FseqFile::open(&input_path, LoadMode::Memory).unwrap();
+        let output = FseqFile::open(&convert_config.output_path, LoadMode::Memory).unwrap();
+        assert_eq!(output.get_version(), (1, 0));
+        assert_eq!(output.get_channel_count(), 70);
+
+        for frame in 0..output.get_frame_count() {
+            let source = input.get_frame(frame).unwrap();
+            let dense = output.get_frame(frame).unwrap();
+            for segment in input.frame_segments(&source) {
+                let start = segment.start_channel as usize;
+                assert_eq!(&dense[start..start + segment.data.len()], segment.data);
+            }
+            assert!(dense[14..50].iter().all(|value| *value == 0));
+        }
+
+        // Converting back to v2 keeps the now-dense layout.
+        let round_trip = config(convert_config.output_path.clone(), directory.file("round_trip.fseq"), FseqVersion::V2);
+        convert_sequence(&round_trip).unwrap();
+        let round_trip = FseqFile::open(&round_trip.output_path, LoadMode::Memory).unwrap();
+        assert_eq!(round_trip.get_channel_ranges(), &[] as &[ChannelRange]);
+        assert_eq!(round_trip.get_frame(7).unwrap(), output.get_frame(7).unwrap());
+    }
+
+    #[test]
+    fn converts_a_file_in_place() {
+        let directory = TempDir::new("convert-in-place");
+        let path = directory.file("show.fseq");
+        std::fs::write(&path, V2Fixture::new(CompressionType::Zstd, 200, 30, 50).build()).unwrap();
+
+        let mut convert_config = config(path.clone(), path.clone(), FseqVersion::V2);
+        convert_config.compression = Some(CompressionType::Zlib);
+        convert_sequence(&convert_config).unwrap();
+
+        let converted = FseqFile::open(&path, LoadMode::Memory).unwrap();
+        assert_eq!(converted.header.compression_type, CompressionType::Zlib);
+        for frame in 0..converted.get_frame_count() {
+            assert_eq!(converted.get_frame(frame).unwrap(), test_data::frame_data(frame, 30));
+        }
+
+        // Only the converted file is left behind.
+        assert_eq!(std::fs::read_dir(directory.path()).unwrap().count(), 1);
+    }
+}
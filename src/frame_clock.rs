@@ -0,0 +1,168 @@
+// src/frame_clock.rs
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Frames sent more than this long after their deadline are counted as late.
+pub const LATE_FRAME_THRESHOLD: Duration = Duration::from_millis(5);
+
+// ----------------------------------------------------------------------
+// FrameClock Struct
+// ----------------------------------------------------------------------
+/// Monotonic deadline schedule for a sequence of frames.
+///
+/// Frame N is due at `origin + (N - origin_frame) * step`, computed from a fixed origin
+/// instead of accumulated sleeps, so time spent decoding and sending never adds up to drift.
+#[derive(Debug, Clone, Copy)]
+pub struct FrameClock {
+    step: Duration,
+    origin: Instant,
+    origin_frame: u32,
+}
+
+impl FrameClock {
+    /// Creates a clock on which `first_frame` is due at `origin`.
+    pub fn new(step: Duration, origin: Instant, first_frame: u32) -> Self {
+        FrameClock { step, origin, origin_frame: first_frame }
+    }
+
+    pub fn step(&self) -> Duration {
+        self.step
+    }
+
+    /// The instant `frame` is due. Frames before the origin frame are due at the origin.
+    pub fn deadline(&self, frame: u32) -> Instant {
+        self.origin + self.step * frame.saturating_sub(self.origin_frame)
+    }
+
+    /// The instant frame 0 is due on this clock, if it is representable.
+    pub fn sequence_start(&self) -> Option<Instant> {
+        self.origin.checked_sub(self.step * self.origin_frame)
+    }
+
+    /// The frame whose slot contains `now`, i.e. the latest frame that is already due.
+    pub fn due_frame(&self, now: Instant) -> u32 {
+        if self.step.is_zero() {
+            return self.origin_frame;
+        }
+        let elapsed = now.saturating_duration_since(self.origin);
+        let slots = elapsed.as_nanos() / self.step.as_nanos();
+        self.origin_frame.saturating_add(slots.min(u32::MAX as u128) as u32)
+    }
+
+    /// Re-anchors the clock so `frame` is due at `at`, e.g. when a looping sequence wraps to
+    /// frame 0 at the deadline of the frame after its last one.
+    pub fn rebase(&mut self, frame: u32, at: Instant) {
+        self.origin = at;
+        self.origin_frame = frame;
+    }
+}
+
+// ----------------------------------------------------------------------
+// DriftStats Struct
+// ----------------------------------------------------------------------
+/// Timing statistics for frames sent against a `FrameClock`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DriftStats {
+    pub frames_sent: u64,
+    /// Frames sent more than `LATE_FRAME_THRESHOLD` after their deadline.
+    pub late_frames: u64,
+    /// Frames dropped because the player fell a whole step or more behind.
+    pub frames_skipped: u64,
+    pub max_lateness: Duration,
+    pub total_lateness: Duration,
+}
+
+impl DriftStats {
+    /// Records a frame sent `lateness` after its deadline.
+    pub fn record_sent(&mut self, lateness: Duration) {
+        self.frames_sent += 1;
+        self.total_lateness += lateness;
+        self.max_lateness = self.max_lateness.max(lateness);
+        if lateness > LATE_FRAME_THRESHOLD {
+            self.late_frames += 1;
+        }
+    }
+
+    pub fn record_skipped(&mut self, frames: u32) {
+        self.frames_skipped += frames as u64;
+    }
+
+    pub fn mean_lateness(&self) -> Duration {
+        if self.frames_sent == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_nanos((self.total_lateness.as_nanos() / self.frames_sent as u128) as u64)
+    }
+
+    /// Whether any frame was late or skipped.
+    pub fn has_drift(&self) -> bool {
+        self.late_frames > 0 || self.frames_skipped > 0
+    }
+}
+
+impl fmt::Display for DriftStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} frames sent, {} late (>{}ms), {} skipped, lateness mean {:.2}ms / max {:.2}ms",
+            self.frames_sent,
+            self.late_frames,
+            LATE_FRAME_THRESHOLD.as_millis(),
+            self.frames_skipped,
+            self.mean_lateness().as_secs_f64() * 1000.0,
+            self.max_lateness.as_secs_f64() * 1000.0,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEP: Duration = Duration::from_millis(25);
+
+    #[test]
+    fn deadlines_are_computed_from_the_origin() {
+        let origin = Instant::now();
+        let clock = FrameClock::new(STEP, origin, 10);
+
+        assert_eq!(clock.deadline(10), origin);
+        assert_eq!(clock.deadline(14), origin + STEP * 4);
+        assert_eq!(clock.deadline(3), origin);
+
+        assert_eq!(clock.due_frame(origin), 10);
+        assert_eq!(clock.due_frame(origin + STEP - Duration::from_nanos(1)), 10);
+        assert_eq!(clock.due_frame(origin + STEP * 7 + Duration::from_millis(3)), 17);
+    }
+
+    #[test]
+    fn rebases_on_loop_wrap() {
+        let origin = Instant::now();
+        let mut clock = FrameClock::new(STEP, origin, 0);
+
+        // A 40 frame sequence wraps to frame 0 at the deadline of frame 40.
+        let wrap = clock.deadline(40);
+        clock.rebase(0, wrap);
+
+        assert_eq!(clock.deadline(0), origin + STEP * 40);
+        assert_eq!(clock.deadline(2), origin + STEP * 42);
+        assert_eq!(clock.due_frame(origin + STEP * 45), 5);
+    }
+
+    #[test]
+    fn accumulates_drift_statistics() {
+        let mut stats = DriftStats::default();
+        assert!(!stats.has_drift());
+
+        stats.record_sent(Duration::from_millis(1));
+        stats.record_sent(Duration::from_millis(9));
+        stats.record_skipped(3);
+
+        assert_eq!(stats.frames_sent, 2);
+        assert_eq!(stats.late_frames, 1);
+        assert_eq!(stats.frames_skipped, 3);
+        assert_eq!(stats.max_lateness, Duration::from_millis(9));
+        assert_eq!(stats.mean_lateness(), Duration::from_millis(5));
+        assert!(stats.has_drift());
+    }
+}
@@ -0,0 +1,1182 @@
+// src/fseq.rs
+use std::borrow::Cow;
+use std::io::Read;
+use std::path::Path;
+use serde::Serialize;
+use zstd::stream::Decoder as ZstdDecoder;
+use flate2::read::ZlibDecoder;
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock, PoisonError};
+use std::sync::mpsc::{channel, Sender};
+use std::thread;
+use crate::block_cache::{BlockCache, DEFAULT_BLOCK_CACHE_BUDGET, SharedBlockCache};
+use crate::fseq_error::{FseqError, FseqResult, IssueKind, ValidationIssue, ValidationReport};
+use crate::fseq_storage::{FseqStorage, LoadMode};
+use crate::inspect::SequenceInfo;
+
+#[cfg(not(any(feature = "native", feature = "tinyfseq")))]
+compile_error!("Enable at least one FSEQ parser backend: the `native` or `tinyfseq` feature.");
+
+/// Parser backend used by `FseqFile::parse`: the pure-Rust parser when enabled, tinyfseq otherwise.
+#[cfg(feature = "native")]
+pub type DefaultParser = crate::fseq_native::NativeParser;
+#[cfg(not(feature = "native"))]
+pub type DefaultParser = crate::fseq_ffi::TinyFseqParser;
+
+/// Size of the fixed FSEQ v2 header; the compression block index starts right after it.
+pub const FIXED_HEADER_SIZE: usize = 32;
+
+/// Size of the fixed FSEQ v1 header; variable headers start right after it.
+pub const V1_FIXED_HEADER_SIZE: usize = 28;
+
+/// Size of a single compression block index entry (u32 first frame + u32 compressed size).
+pub const BLOCK_INDEX_ENTRY_SIZE: usize = 8;
+
+/// Size of a single sparse channel range entry (u24 first channel + u24 channel count).
+pub const CHANNEL_RANGE_ENTRY_SIZE: usize = 6;
+
+/// Size of a variable header's length + code prefix.
+pub const VARIABLE_HEADER_PREFIX_SIZE: usize = 4;
+
+// ----------------------------------------------------------------------
+// FseqHeader Struct
+// ----------------------------------------------------------------------
+/// Frame data compression used by an FSEQ file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompressionType {
+    None,
+    Zstd,
+    Zlib,
+}
+
+impl CompressionType {
+    /// Maps the low nibble of header byte 20 to a compression type.
+    pub fn from_raw(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(CompressionType::None),
+            1 => Some(CompressionType::Zstd),
+            2 => Some(CompressionType::Zlib),
+            _ => None,
+        }
+    }
+
+    /// Returns the value stored in the low nibble of header byte 20.
+    pub fn to_raw(self) -> u8 {
+        match self {
+            CompressionType::None => 0,
+            CompressionType::Zstd => 1,
+            CompressionType::Zlib => 2,
+        }
+    }
+}
+
+impl std::fmt::Display for CompressionType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CompressionType::None => write!(f, "none"),
+            CompressionType::Zstd => write!(f, "zstd"),
+            CompressionType::Zlib => write!(f, "zlib"),
+        }
+    }
+}
+
+/// The fixed FSEQ header, independent of the parser backend that read it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FseqHeader {
+    pub channel_data_offset: u16,
+    pub minor_version: u8,
+    pub major_version: u8,
+    /// Offset of the variable headers (the fixed header length in v1).
+    pub variable_data_offset: u16,
+    pub channel_count: u32,
+    pub frame_count: u32,
+    pub step_time_ms: u8,
+    pub compression_type: CompressionType,
+    /// Extended 12-bit compression block count (upper nibble of byte 20 + byte 21).
+    pub compression_block_count: u16,
+    pub channel_range_count: u8,
+    pub sequence_uid: u64,
+}
+
+/// A raw variable header entry as stored in the file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawVariableHeader {
+    /// Total entry size in bytes, including the 4-byte prefix.
+    pub size: u16,
+    pub code: [u8; 2],
+    pub data: Vec<u8>,
+}
+
+// ----------------------------------------------------------------------
+// FseqParser Trait
+// ----------------------------------------------------------------------
+/// Reads the on-disk FSEQ v2 structures. Implemented by the pure-Rust parser and the tinyfseq FFI.
+pub trait FseqParser {
+    /// Reads the 32-byte fixed v2 header.
+    fn read_header(buffer: &[u8]) -> FseqResult<FseqHeader>;
+    /// Reads one compression block index entry, returning (first frame, compressed size).
+    fn read_compression_block(entry: &[u8]) -> FseqResult<(u32, u32)>;
+    /// Reads one sparse channel range entry.
+    fn read_channel_range(entry: &[u8]) -> FseqResult<ChannelRange>;
+    /// Reads one variable header from the start of `data`.
+    fn read_variable_header(data: &[u8]) -> FseqResult<RawVariableHeader>;
+}
+
+// ----------------------------------------------------------------------
+// CompressionBlock Struct
+// ----------------------------------------------------------------------
+/// A compression block resolved from the FSEQ v2 block index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct CompressionBlock {
+    /// First frame stored in this block.
+    pub start_frame: u32,
+    /// Number of frames stored in this block.
+    pub frame_count: u32,
+    /// Absolute byte offset of the compressed data within the file.
+    pub offset: u64,
+    /// Size of the compressed data in bytes.
+    pub size: usize,
+}
+
+// ----------------------------------------------------------------------
+// Sparse Channel Range Structs
+// ----------------------------------------------------------------------
+/// A contiguous range of absolute channels stored in each frame of a sparse FSEQ file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ChannelRange {
+    /// First absolute channel (0-based) covered by this range.
+    pub start_channel: u32,
+    /// Number of channels in this range.
+    pub channel_count: u32,
+}
+
+/// A slice of frame data together with the absolute channel it starts at.
+#[derive(Debug, Clone, Copy)]
+pub struct FrameSegment<'a> {
+    pub start_channel: u32,
+    pub data: &'a [u8],
+}
+
+// ----------------------------------------------------------------------
+// VariableHeader Enum
+// ----------------------------------------------------------------------
+/// A variable header stored between the fixed header tables and the channel data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VariableHeader {
+    /// `mf`: media (audio) filename the sequence was built against.
+    MediaFilename(String),
+    /// `sp`: sequence producer, i.e. the software that wrote the file.
+    SequenceProducer(String),
+    /// Any other two-letter code, kept as raw bytes.
+    Unknown { code: [u8; 2], data: Vec<u8> },
+}
+
+impl VariableHeader {
+    /// Builds a typed variable header from its two-letter code and raw value.
+    pub fn from_raw(code: [u8; 2], data: &[u8]) -> Self {
+        match &code {
+            b"mf" => VariableHeader::MediaFilename(Self::decode_string(data)),
+            b"sp" => VariableHeader::SequenceProducer(Self::decode_string(data)),
+            _ => VariableHeader::Unknown { code, data: data.to_vec() },
+        }
+    }
+
+    /// Returns the two-letter code identifying this header.
+    pub fn code(&self) -> [u8; 2] {
+        match self {
+            VariableHeader::MediaFilename(_) => *b"mf",
+            VariableHeader::SequenceProducer(_) => *b"sp",
+            VariableHeader::Unknown { code, .. } => *code,
+        }
+    }
+
+    /// Returns the value for display: the string for known headers, a byte dump otherwise.
+    pub fn display_value(&self) -> String {
+        match self {
+            VariableHeader::MediaFilename(value) | VariableHeader::SequenceProducer(value) => value.clone(),
+            VariableHeader::Unknown { data, .. } => format!("{} bytes {:02X?}", data.len(), data),
+        }
+    }
+
+    /// Returns the raw value as written to the file; string values get their NUL terminator back.
+    pub fn to_raw_value(&self) -> Vec<u8> {
+        match self {
+            VariableHeader::MediaFilename(value) | VariableHeader::SequenceProducer(value) => {
+                let mut data = value.as_bytes().to_vec();
+                data.push(0);
+                data
+            }
+            VariableHeader::Unknown { data, .. } => data.clone(),
+        }
+    }
+
+    /// Values are NUL-terminated strings; anything after the first NUL is padding.
+    fn decode_string(data: &[u8]) -> String {
+        let end = data.iter().position(|byte| *byte == 0).unwrap_or(data.len());
+        String::from_utf8_lossy(&data[..end]).into_owned()
+    }
+}
+
+impl std::fmt::Display for VariableHeader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let code = self.code();
+        write!(f, "{}{}: {}", code[0] as char, code[1] as char, self.display_value())
+    }
+}
+
+/// A block handed to the prefetch worker, which reads and decompresses it in the background.
+struct PrefetchJob {
+    block_index: usize,
+    block: CompressionBlock,
+}
+
+// ----------------------------------------------------------------------
+// FseqFile Struct
+// ----------------------------------------------------------------------
+/// Represents a parsed FSEQ file, holding the raw data and header information.
+pub struct FseqFile {
+    /// Backing bytes of the whole file: in memory, memory-mapped or read on demand.
+    /// Shared with the prefetch worker, which reads compressed blocks itself.
+    storage: Arc<FseqStorage>,
+    pub header: FseqHeader,
+    /// Compression block index, empty for uncompressed and single-stream files.
+    blocks: Vec<CompressionBlock>,
+    /// Sparse channel range table, empty when frames hold contiguous channels starting at 0.
+    channel_ranges: Vec<ChannelRange>,
+    /// Absolute channel layout of each frame: the sparse ranges, or one range covering all channels.
+    frame_layout: Vec<ChannelRange>,
+    variable_headers: Vec<VariableHeader>,
+    /// Fully decompressed frame data for single-stream files (block count 0), filled on first
+    /// access. A decode failure is kept too, unless a retry could get past it.
+    single_stream_data: OnceLock<FseqResult<Vec<u8>>>,
+    /// LRU cache of decompressed blocks, shared with the prefetch worker.
+    block_cache: Arc<SharedBlockCache>,
+    prefetch_enabled: bool,
+    /// Job queue of the prefetch worker thread, spawned on first use.
+    prefetch_tx: Mutex<Option<Sender<PrefetchJob>>>,
+}
+
+impl FseqFile {
+    /// Parses the raw buffer to initialize the file's header, using the default parser backend.
+    pub fn parse(buffer: Vec<u8>) -> FseqResult<Self> {
+        Self::parse_with::<DefaultParser>(buffer)
+    }
+
+    /// Parses the raw buffer with an explicit parser backend.
+    pub fn parse_with<P: FseqParser>(buffer: Vec<u8>) -> FseqResult<Self> {
+        Self::from_storage_with::<P>(FseqStorage::from(buffer))
+    }
+
+    /// Opens and parses the file at `path`, loading its frame data according to `load_mode`.
+    pub fn open(path: impl AsRef<Path>, load_mode: LoadMode) -> FseqResult<Self> {
+        Self::from_storage_with::<DefaultParser>(FseqStorage::open(path, load_mode)?)
+    }
+
+    /// Parses the header region of `storage` with an explicit parser backend.
+    /// Only the bytes before the channel data are read up front; frames are read on demand.
+    pub fn from_storage_with<P: FseqParser>(storage: FseqStorage) -> FseqResult<Self> {
+        if storage.len() < V1_FIXED_HEADER_SIZE as u64 {
+            return Err(FseqError::HeaderTooSmall { size: storage.len() as usize, required: V1_FIXED_HEADER_SIZE });
+        }
+
+        let fixed_header = storage.read_at(0, storage.len().min(FIXED_HEADER_SIZE as u64) as usize)?;
+
+        // Byte 7 holds the major version; v1 files use a different fixed header that tinyfseq does not read.
+        let header = match fixed_header[7] {
+            1 => Self::read_v1_header(&fixed_header)?,
+            _ => P::read_header(&fixed_header)?,
+        };
+
+        // At most 64 KiB, bounded by the 16-bit channel data offset.
+        let header_len = (header.channel_data_offset as u64).clamp(fixed_header.len() as u64, storage.len()) as usize;
+        let buffer = storage.read_at(0, header_len)?.into_owned();
+
+        let blocks = if header.compression_type == CompressionType::None {
+            Vec::new()
+        } else {
+            Self::read_block_index::<P>(&buffer, &header)?
+        };
+
+        let channel_ranges = Self::read_channel_ranges::<P>(&buffer, &header)?;
+        let frame_layout = if channel_ranges.is_empty() {
+            vec![ChannelRange { start_channel: 0, channel_count: header.channel_count }]
+        } else {
+            channel_ranges.clone()
+        };
+
+        let variable_headers = Self::read_variable_headers::<P>(&buffer, &header)?;
+
+        let fseq_file = FseqFile {
+            storage: Arc::new(storage),
+            header,
+            blocks,
+            channel_ranges,
+            frame_layout,
+            variable_headers,
+            single_stream_data: OnceLock::new(),
+            block_cache: Arc::new(SharedBlockCache::new(DEFAULT_BLOCK_CACHE_BUDGET)),
+            prefetch_enabled: true,
+            prefetch_tx: Mutex::new(None),
+        };
+
+        Ok(fseq_file)
+    }
+
+    /// Reads an FSEQ v1 fixed header and maps it onto the v2 header structure.
+    ///
+    /// v1 layout: magic (0-3), channel data offset (4-5), minor/major version (6-7),
+    /// fixed header length (8-9), channel count (10-13), frame count (14-17), step time (18-19),
+    /// followed by universe, gamma and color fields that playback does not use.
+    /// v1 files are never compressed and have no sparse ranges; variable headers start
+    /// right after the fixed header.
+    fn read_v1_header(buffer: &[u8]) -> FseqResult<FseqHeader> {
+        let magic = [buffer[0], buffer[1], buffer[2], buffer[3]];
+        if &magic != b"PSEQ" && &magic != b"FSEQ" {
+            return Err(FseqError::InvalidMagic(magic));
+        }
+
+        let read_u16 = |offset: usize| u16::from_le_bytes([buffer[offset], buffer[offset + 1]]);
+        let read_u32 = |offset: usize| u32::from_le_bytes([buffer[offset], buffer[offset + 1], buffer[offset + 2], buffer[offset + 3]]);
+
+        let step_time_ms = read_u16(18);
+        if step_time_ms == 0 || step_time_ms > u8::MAX as u16 {
+            return Err(FseqError::InvalidHeader(format!("v1 step time of {}ms is unsupported (must be 1-255ms)", step_time_ms)));
+        }
+
+        let header = FseqHeader {
+            channel_data_offset: read_u16(4),
+            minor_version: buffer[6],
+            major_version: buffer[7],
+            variable_data_offset: read_u16(8),
+            channel_count: read_u32(10),
+            frame_count: read_u32(14),
+            step_time_ms: step_time_ms as u8,
+            compression_type: CompressionType::None,
+            compression_block_count: 0,
+            channel_range_count: 0,
+            sequence_uid: 0,
+        };
+
+        if (header.variable_data_offset as usize) < V1_FIXED_HEADER_SIZE || header.channel_data_offset < header.variable_data_offset {
+            return Err(FseqError::InvalidHeader(format!("v1 header has invalid offsets (header length {}, channel data offset {})",
+                                                        header.variable_data_offset, header.channel_data_offset)));
+        }
+
+        Ok(header)
+    }
+
+    /// Sets the memory budget (in bytes) for cached decompressed blocks. Prefetching needs room
+    /// for two blocks; with less, prefetched blocks are dropped rather than evicting the one
+    /// being played.
+    pub fn with_block_cache_budget(self, budget_bytes: usize) -> Self {
+        let largest_block = self.blocks.iter().map(|block| block.frame_count as usize).max().unwrap_or(0) * self.frame_size();
+        if self.prefetch_enabled && budget_bytes < largest_block.saturating_mul(2) {
+            log::warn!(
+                "Block cache budget of {} bytes holds fewer than two {} byte blocks; the next block cannot be prefetched.",
+                budget_bytes, largest_block
+            );
+        }
+        self.lock_block_cache().set_budget(budget_bytes);
+        self
+    }
+
+    /// Enables or disables decompressing the next block on a background thread.
+    pub fn with_prefetch(mut self, enabled: bool) -> Self {
+        self.prefetch_enabled = enabled;
+        self
+    }
+
+    /// Prints a summary of the FSEQ header contents for debugging, in the format of the
+    /// `info` subcommand.
+    pub fn dump_header_info(&self) {
+        println!("\n--- FSEQ Header Dump ---");
+        print!("{}", SequenceInfo::new("sequence", self));
+    }
+    
+    /// Retrieves the frame data. Handles ZLIB/ZSTD decompression if needed.
+    /// Allocates a new buffer per call; playback uses `read_frame_into` to reuse one.
+    pub fn get_frame(&self, frame_num: u32) -> FseqResult<Vec<u8>> {
+        let mut frame_data = vec![0u8; self.frame_size()];
+        self.read_frame_into(frame_num, &mut frame_data)?;
+        Ok(frame_data)
+    }
+
+    /// Size in bytes of a single frame, i.e. the buffer length `read_frame_into` expects.
+    pub fn frame_size(&self) -> usize {
+        self.header.channel_count as usize
+    }
+
+    /// Copies a frame into a caller-supplied buffer of `frame_size()` bytes without allocating.
+    /// Handles ZLIB/ZSTD decompression if needed; decompressed blocks are shared through the block cache.
+    pub fn read_frame_into(&self, frame_num: u32, out: &mut [u8]) -> FseqResult<()> {
+        if frame_num >= self.header.frame_count {
+            return Err(FseqError::FrameOutOfRange { frame: frame_num, frame_count: self.header.frame_count });
+        }
+
+        let frame_size = self.frame_size();
+        if out.len() != frame_size {
+            return Err(FseqError::FrameBufferSize { expected: frame_size, actual: out.len() });
+        }
+        
+        match self.header.compression_type { 
+            CompressionType::None => {
+                // UNCOMPRESSED LOGIC: Frames are stored contiguously starting at channelDataOffset.
+                // Offsets are u64 so frames past 4 GiB stay addressable on 32-bit players;
+                // a u32 frame number times a u32 frame size cannot overflow it.
+                
+                let data_offset = self.header.channel_data_offset as u64;
+                let frame_offset = frame_num as u64 * frame_size as u64;
+                
+                let frame_start = data_offset + frame_offset;
+                self.storage.read_into(frame_start, out)
+            }
+            CompressionType::Zstd | CompressionType::Zlib => {
+                // COMPRESSED LOGIC: ZSTD and ZLIB share the same block table layout, only the decoder differs.
+                
+                if self.blocks.is_empty() {
+                    // Single-stream file: the whole sequence is one compressed stream with no block table.
+                    let decompressed_data = self.get_single_stream_data()?;
+                    return copy_decoded_frame(frame_num, frame_num, decompressed_data, out);
+                }
+
+                // --- Block Lookup (binary search over the block index built in parse) ---
+                let block_index = self.find_block_index(frame_num)?;
+                let block = self.blocks[block_index];
+                let frame_in_block = frame_num - block.start_frame;
+
+                log::trace!("Frame {} resolved to block {} (frame in block={}, offset={}, size={})",
+                            frame_num, block_index, frame_in_block, block.offset, block.size);
+
+                // --- Decompression (cached) & Extraction Steps ---
+                let decompressed_data = self.get_block_data(block_index)?;
+                self.prefetch_block(block_index + 1);
+
+                copy_decoded_frame(frame_num, frame_in_block, &decompressed_data, out)
+            }
+        }
+    }
+    
+    /// Checks the channel data against the header and decodes every frame.
+    ///
+    /// Problems are collected rather than returned early, so one run reports every
+    /// truncated block, bad offset and size mismatch in the file.
+    pub fn validate(&self) -> ValidationReport {
+        let mut issues = Vec::new();
+        let frame_size = self.frame_size();
+        let data_offset = self.header.channel_data_offset as u64;
+        let sequence_size = self.header.frame_count as u64 * frame_size as u64;
+
+        if self.header.step_time_ms == 0 {
+            issues.push(ValidationIssue::error(IssueKind::BadStepTime,
+                "The step time is 0ms, so frames have no playback rate.".to_string()));
+        }
+
+        if data_offset > self.storage.len() {
+            issues.push(ValidationIssue::error(IssueKind::BadOffset, format!(
+                "Channel data offset {} is beyond the end of the file ({} bytes).", data_offset, self.storage.len())));
+        }
+
+        if self.is_sparse() {
+            let range_channels: u64 = self.channel_ranges.iter().map(|range| range.channel_count as u64).sum();
+            if range_channels != self.header.channel_count as u64 {
+                issues.push(ValidationIssue::error(IssueKind::SizeMismatch, format!(
+                    "Sparse channel ranges cover {} channels, but the header declares {}.", range_channels, self.header.channel_count)));
+            }
+        }
+
+        match self.header.compression_type {
+            CompressionType::None => {
+                let expected_end = data_offset + sequence_size;
+                if expected_end > self.storage.len() {
+                    issues.push(ValidationIssue::error(IssueKind::Truncated, format!(
+                        "Channel data ends at byte {}, but the file is only {} bytes ({} of {} frames complete).",
+                        expected_end, self.storage.len(), self.storage.len().saturating_sub(data_offset) / frame_size.max(1) as u64, self.header.frame_count)));
+                } else if expected_end < self.storage.len() {
+                    issues.push(ValidationIssue::warning(IssueKind::TrailingData, format!(
+                        "{} bytes follow the channel data.", self.storage.len() - expected_end)));
+                }
+            }
+            CompressionType::Zstd | CompressionType::Zlib if self.blocks.is_empty() => {
+                if data_offset <= self.storage.len() {
+                    match self.read_single_stream() {
+                        Ok(decompressed_data) if decompressed_data.len() as u64 != sequence_size => {
+                            issues.push(ValidationIssue::error(IssueKind::SizeMismatch, format!(
+                                "Compressed stream decodes to {} bytes, expected {} ({} frames x {} channels).",
+                                decompressed_data.len(), sequence_size, self.header.frame_count, frame_size)));
+                        }
+                        Ok(_) => {}
+                        Err(e) => issues.push(ValidationIssue::error(IssueKind::DecodeFailed, format!(
+                            "Compressed stream failed to decode: {:#}", e))),
+                    }
+                }
+            }
+            CompressionType::Zstd | CompressionType::Zlib => {
+                for (block_index, block) in self.blocks.iter().enumerate() {
+                    let block_end = block.offset + block.size as u64;
+                    if block_end > self.storage.len() {
+                        issues.push(ValidationIssue::error(IssueKind::Truncated, format!(
+                            "Block {} (frames {}-{}) spans bytes {}-{}, past the end of the file ({} bytes).",
+                            block_index, block.start_frame, block.start_frame + block.frame_count - 1, block.offset, block_end, self.storage.len())));
+                        continue;
+                    }
+
+                    let expected_size = block.frame_count as usize * frame_size;
+                    match self.get_compressed_block_slice(block_index)
+                        .and_then(|compressed_data: Cow<'_, [u8]>| decompress_block(self.header.compression_type, &compressed_data))
+                    {
+                        Ok(decompressed_data) if decompressed_data.len() != expected_size => {
+                            issues.push(ValidationIssue::error(IssueKind::SizeMismatch, format!(
+                                "Block {} decodes to {} bytes, expected {} ({} frames x {} channels).",
+                                block_index, decompressed_data.len(), expected_size, block.frame_count, frame_size)));
+                        }
+                        Ok(_) => {}
+                        Err(e) => issues.push(ValidationIssue::error(IssueKind::DecodeFailed, format!(
+                            "Block {} failed to decode: {:#}", block_index, e))),
+                    }
+                }
+
+                if let Some(last) = self.blocks.last() {
+                    let data_end = last.offset + last.size as u64;
+                    if data_end < self.storage.len() {
+                        issues.push(ValidationIssue::warning(IssueKind::TrailingData, format!(
+                            "{} bytes follow the last compression block.", self.storage.len() - data_end)));
+                    }
+                }
+            }
+        }
+
+        // Decode every frame through the playback path; only the first failure of each run is reported.
+        let mut frame_data = vec![0u8; frame_size];
+        let mut frames_decoded = 0;
+        let mut failing_since: Option<(u32, String)> = None;
+        for frame_num in 0..self.header.frame_count {
+            match self.read_frame_into(frame_num, &mut frame_data) {
+                Ok(()) => {
+                    frames_decoded += 1;
+                    if let Some((first_failed, error)) = failing_since.take() {
+                        issues.push(ValidationIssue::frames_failed(first_failed, frame_num - 1, &error));
+                    }
+                }
+                Err(e) => {
+                    if failing_since.is_none() {
+                        failing_since = Some((frame_num, format!("{:#}", e)));
+                    }
+                }
+            }
+        }
+        if let Some((first_failed, error)) = failing_since {
+            issues.push(ValidationIssue::frames_failed(first_failed, self.header.frame_count - 1, &error));
+        }
+
+        ValidationReport { frames_decoded, issues }
+    }
+
+    /// Size of the underlying file in bytes.
+    pub fn file_size(&self) -> u64 {
+        self.storage.len()
+    }
+
+    /// Builds the compression block index from the block table that follows the fixed header.
+    ///
+    /// Each table entry stores the block's first frame and compressed size. Byte offsets are
+    /// accumulated from `channelDataOffset`, and each block spans up to the next block's first frame.
+    /// Unused (zero-sized) trailing entries written by FPP are skipped.
+    fn read_block_index<P: FseqParser>(buffer: &[u8], header: &FseqHeader) -> FseqResult<Vec<CompressionBlock>> {
+        let block_count = header.compression_block_count as usize;
+        let table_end = FIXED_HEADER_SIZE + block_count * BLOCK_INDEX_ENTRY_SIZE;
+
+        if table_end > buffer.len() {
+            return Err(FseqError::InvalidHeader(format!("compression block index ({} blocks) ends at offset {}, past the header region", block_count, table_end)));
+        }
+
+        let mut blocks: Vec<CompressionBlock> = Vec::with_capacity(block_count);
+        let mut offset = header.channel_data_offset as u64;
+
+        for index in 0..block_count {
+            let entry_start = FIXED_HEADER_SIZE + index * BLOCK_INDEX_ENTRY_SIZE;
+            let (first_frame, size) = P::read_compression_block(&buffer[entry_start..entry_start + BLOCK_INDEX_ENTRY_SIZE])
+                .map_err(|e| FseqError::InvalidHeader(format!("compression block {}: {}", index, e)))?;
+
+            if size == 0 {
+                continue;
+            }
+
+            if let Some(previous) = blocks.last()
+                && first_frame <= previous.start_frame
+            {
+                return Err(FseqError::InvalidHeader(format!("compression block {} starts at frame {}, which does not follow the previous block (frame {})",
+                                                            index, first_frame, previous.start_frame)));
+            }
+
+            if first_frame >= header.frame_count {
+                return Err(FseqError::InvalidHeader(format!("compression block {} starts at frame {}, beyond the frame count ({})", index, first_frame, header.frame_count)));
+            }
+
+            blocks.push(CompressionBlock {
+                start_frame: first_frame,
+                frame_count: 0,
+                offset,
+                size: size as usize,
+            });
+            offset += size as u64;
+        }
+
+        // Each block spans up to the next block's first frame; the last one runs to the end of the sequence.
+        let next_starts: Vec<u32> = blocks.iter().skip(1).map(|block| block.start_frame)
+            .chain(std::iter::once(header.frame_count))
+            .collect();
+        for (block, next_start) in blocks.iter_mut().zip(next_starts) {
+            block.frame_count = next_start - block.start_frame;
+        }
+
+        if let Some(first) = blocks.first()
+            && first.start_frame != 0
+        {
+            return Err(FseqError::InvalidHeader(format!("first compression block starts at frame {} instead of frame 0", first.start_frame)));
+        }
+
+        Ok(blocks)
+    }
+
+    /// Reads the sparse channel range table that follows the compression block index.
+    fn read_channel_ranges<P: FseqParser>(buffer: &[u8], header: &FseqHeader) -> FseqResult<Vec<ChannelRange>> {
+        let range_count = header.channel_range_count as usize;
+        let table_start = FIXED_HEADER_SIZE + header.compression_block_count as usize * BLOCK_INDEX_ENTRY_SIZE;
+        let table_end = table_start + range_count * CHANNEL_RANGE_ENTRY_SIZE;
+
+        if table_end > buffer.len() {
+            return Err(FseqError::InvalidHeader(format!("sparse channel range table ({} ranges) ends at offset {}, past the header region", range_count, table_end)));
+        }
+
+        let mut channel_ranges = Vec::with_capacity(range_count);
+
+        for index in 0..range_count {
+            let entry_start = table_start + index * CHANNEL_RANGE_ENTRY_SIZE;
+            let channel_range = P::read_channel_range(&buffer[entry_start..entry_start + CHANNEL_RANGE_ENTRY_SIZE])
+                .map_err(|e| FseqError::InvalidHeader(format!("sparse channel range {}: {}", index, e)))?;
+            channel_ranges.push(channel_range);
+        }
+
+        let sparse_channel_total: u64 = channel_ranges.iter().map(|range| range.channel_count as u64).sum();
+        if sparse_channel_total > header.channel_count as u64 {
+            return Err(FseqError::InvalidHeader(format!("sparse channel ranges cover {} channels, but frames only hold {} channels", sparse_channel_total, header.channel_count)));
+        }
+
+        Ok(channel_ranges)
+    }
+
+    /// Reads the variable headers stored between `variableDataOffset` and `channelDataOffset`.
+    /// Each entry is a u16 length (including its 4-byte header), a two-letter code and the value.
+    fn read_variable_headers<P: FseqParser>(buffer: &[u8], header: &FseqHeader) -> FseqResult<Vec<VariableHeader>> {
+        let region_end = (header.channel_data_offset as usize).min(buffer.len());
+        let mut offset = header.variable_data_offset as usize;
+        let mut variable_headers = Vec::new();
+
+        while offset + VARIABLE_HEADER_PREFIX_SIZE <= region_end {
+            let declared_size = u16::from_le_bytes([buffer[offset], buffer[offset + 1]]) as usize;
+
+            // Writers pad the region up to channelDataOffset with zeros.
+            if declared_size < VARIABLE_HEADER_PREFIX_SIZE {
+                break;
+            }
+
+            let raw_header = P::read_variable_header(&buffer[offset..region_end])
+                .map_err(|e| FseqError::InvalidHeader(format!("variable header at offset {}: {}", offset, e)))?;
+
+            variable_headers.push(VariableHeader::from_raw(raw_header.code, &raw_header.data));
+            offset += raw_header.size as usize;
+        }
+
+        Ok(variable_headers)
+    }
+
+    /// Finds the index of the compression block containing `frame_num` via binary search.
+    pub(crate) fn find_block_index(&self, frame_num: u32) -> FseqResult<usize> {
+        let block_index = self.blocks.partition_point(|block| block.start_frame <= frame_num);
+
+        if block_index == 0 {
+            return Err(FseqError::FrameOutOfRange { frame: frame_num, frame_count: self.header.frame_count });
+        }
+
+        Ok(block_index - 1)
+    }
+
+    /// Returns the decompressed data of a single-stream file, running the decompressor only on first use.
+    fn get_single_stream_data(&self) -> FseqResult<&[u8]> {
+        if self.single_stream_data.get().is_none() {
+            match self.read_single_stream() {
+                // Another thread may have won the race; either result is identical.
+                Ok(decompressed_data) => {
+                    let _ = self.single_stream_data.set(Ok(decompressed_data));
+                }
+                Err(e) => {
+                    if let Some(failure) = e.cached_copy() {
+                        let _ = self.single_stream_data.set(Err(failure));
+                    }
+                    return Err(e);
+                }
+            }
+        }
+
+        match self.single_stream_data.get().expect("single stream data was just initialized") {
+            Ok(decompressed_data) => Ok(decompressed_data),
+            Err(e) => Err(e.cached_copy().expect("only cacheable failures are kept")),
+        }
+    }
+
+    /// Reads and decompresses the single compressed stream that runs from `channelDataOffset` to the end of the file.
+    fn read_single_stream(&self) -> FseqResult<Vec<u8>> {
+        let compressed_data_section_start = self.header.channel_data_offset as u64;
+        if compressed_data_section_start > self.storage.len() {
+            return Err(FseqError::Truncated { start: compressed_data_section_start, end: compressed_data_section_start, file_size: self.storage.len() });
+        }
+
+        let compressed_size = self.storage.len() - compressed_data_section_start;
+        let compressed_size = usize::try_from(compressed_size).map_err(|_| FseqError::DataTooLarge { size: compressed_size })?;
+        let compressed_data = self.storage.read_at(compressed_data_section_start, compressed_size)?;
+        decompress_block(self.header.compression_type, &compressed_data)
+    }
+
+    /// Returns the decompressed contents of a block, decompressing and caching it on a miss.
+    /// Blocks that failed to decode fail again from the cache without being re-read, and a
+    /// block the prefetch worker is decompressing is waited for rather than decoded again.
+    pub(crate) fn get_block_data(&self, block_index: usize) -> FseqResult<Arc<Vec<u8>>> {
+        {
+            let mut block_cache = self.lock_block_cache();
+            if block_cache.is_pending(block_index) {
+                block_cache = self.block_cache.wait_for(block_cache, block_index);
+            }
+            if let Some(decompressed_data) = block_cache.get(block_index) {
+                return Ok(decompressed_data);
+            }
+            if let Some(failure) = block_cache.failure(block_index) {
+                return Err(failure);
+            }
+        }
+
+        let result = self.get_compressed_block_slice(block_index)
+            .and_then(|compressed_data_slice| decompress_block(self.header.compression_type, &compressed_data_slice));
+
+        match result {
+            Ok(decompressed_data) => {
+                let decompressed_data = Arc::new(decompressed_data);
+                self.lock_block_cache().insert(block_index, decompressed_data.clone());
+                Ok(decompressed_data)
+            }
+            Err(e) => {
+                self.lock_block_cache().insert_failure(block_index, &e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Returns the compressed bytes of a block, checking the block boundaries against the file size.
+    /// Borrowed for in-memory and mapped files, read from disk for streamed ones.
+    fn get_compressed_block_slice(&self, block_index: usize) -> FseqResult<Cow<'_, [u8]>> {
+        read_compressed_block(&self.storage, block_index, self.blocks[block_index])
+    }
+
+    /// Queues a block for background decompression if it is not already cached or queued.
+    pub(crate) fn prefetch_block(&self, block_index: usize) {
+        if !self.prefetch_enabled || block_index >= self.blocks.len() {
+            return;
+        }
+
+        if !self.lock_block_cache().mark_pending(block_index) {
+            return;
+        }
+
+        // The worker reads the compressed bytes too, so streamed files never block playback on disk.
+        let mut prefetch_tx = self.prefetch_tx.lock().unwrap_or_else(PoisonError::into_inner);
+        let sender = prefetch_tx.get_or_insert_with(|| {
+            spawn_prefetch_worker(self.storage.clone(), self.block_cache.clone(), self.header.compression_type)
+        });
+
+        if sender.send(PrefetchJob { block_index, block: self.blocks[block_index] }).is_err() {
+            // The worker is gone; drop the sender so the next prefetch respawns it.
+            *prefetch_tx = None;
+            self.lock_block_cache().clear_pending(block_index);
+            self.block_cache.notify_resolved();
+        }
+    }
+
+    fn lock_block_cache(&self) -> MutexGuard<'_, BlockCache> {
+        self.block_cache.lock()
+    }
+
+    // Getters
+    pub fn get_step_time(&self) -> i32 {
+        self.header.step_time_ms as i32
+    }
+
+    pub fn get_channel_count(&self) -> u32 {
+        self.header.channel_count
+    }
+
+    pub fn get_frame_count(&self) -> u32 {
+        self.header.frame_count
+    }
+
+    /// Returns the (major, minor) FSEQ format version.
+    pub fn get_version(&self) -> (u8, u8) {
+        (self.header.major_version, self.header.minor_version)
+    }
+
+    pub fn get_compression_blocks(&self) -> &[CompressionBlock] {
+        &self.blocks
+    }
+
+    pub fn get_channel_ranges(&self) -> &[ChannelRange] {
+        &self.channel_ranges
+    }
+
+    pub fn variable_headers(&self) -> &[VariableHeader] {
+        &self.variable_headers
+    }
+
+    /// Returns the `mf` (media filename) variable header, if present.
+    pub fn get_media_filename(&self) -> Option<&str> {
+        self.variable_headers.iter().find_map(|variable_header| match variable_header {
+            VariableHeader::MediaFilename(filename) => Some(filename.as_str()),
+            _ => None,
+        })
+    }
+
+    pub fn is_sparse(&self) -> bool {
+        !self.channel_ranges.is_empty()
+    }
+
+    /// Splits a frame returned by `get_frame` into segments placed at their absolute channel offsets.
+    /// Non-sparse files yield a single segment starting at channel 0.
+    pub fn frame_segments<'a>(&'a self, frame_data: &'a [u8]) -> impl Iterator<Item = FrameSegment<'a>> + 'a {
+        let mut data_offset = 0usize;
+
+        self.frame_layout.iter().filter_map(move |range| {
+            let data_start = data_offset.min(frame_data.len());
+            let data_end = (data_offset + range.channel_count as usize).min(frame_data.len());
+            data_offset += range.channel_count as usize;
+
+            if data_start == data_end {
+                return None;
+            }
+
+            Some(FrameSegment {
+                start_channel: range.start_channel,
+                data: &frame_data[data_start..data_end],
+            })
+        })
+    }
+}
+
+/// Reads the compressed bytes of `block` from `storage`, failing if it runs past the end of the file.
+fn read_compressed_block(storage: &FseqStorage, block_index: usize, block: CompressionBlock) -> FseqResult<Cow<'_, [u8]>> {
+    let compressed_chunk_start = block.offset;
+    let compressed_chunk_end = compressed_chunk_start + block.size as u64;
+
+    if compressed_chunk_end > storage.len() {
+        return Err(FseqError::TruncatedBlock { block: block_index, start: compressed_chunk_start, end: compressed_chunk_end, file_size: storage.len() });
+    }
+
+    storage.read_at(compressed_chunk_start, block.size)
+}
+
+/// Copies frame `frame_in_data` of a decompressed block or stream into `out`, failing if the
+/// data is too short to hold it.
+fn copy_decoded_frame(frame_num: u32, frame_in_data: u32, decompressed_data: &[u8], out: &mut [u8]) -> FseqResult<()> {
+    let frame_start = (frame_in_data as usize).checked_mul(out.len());
+    let frame_end = frame_start.and_then(|frame_start| frame_start.checked_add(out.len()));
+
+    match (frame_start, frame_end) {
+        (Some(frame_start), Some(frame_end)) if frame_end <= decompressed_data.len() => {
+            out.copy_from_slice(&decompressed_data[frame_start..frame_end]);
+            Ok(())
+        }
+        _ => Err(FseqError::DecodedSizeMismatch { frame: frame_num, available: decompressed_data.len(), required: frame_end.unwrap_or(usize::MAX) }),
+    }
+}
+
+/// Decompresses a single compressed block using the decoder matching the file's compression type.
+fn decompress_block(compression_type: CompressionType, compressed_data: &[u8]) -> FseqResult<Vec<u8>> {
+    let mut decompressed_data = Vec::new();
+
+    match compression_type {
+        CompressionType::Zstd => {
+            let decompress_failed = |source| FseqError::DecompressFailed { compression: compression_type, source };
+            let mut decoder = ZstdDecoder::new(compressed_data).map_err(decompress_failed)?;
+            decoder.read_to_end(&mut decompressed_data).map_err(decompress_failed)?;
+        }
+        CompressionType::Zlib => {
+            let mut decoder = ZlibDecoder::new(compressed_data);
+            decoder.read_to_end(&mut decompressed_data)
+                .map_err(|source| FseqError::DecompressFailed { compression: compression_type, source })?;
+        }
+        CompressionType::None => {
+            return Err(FseqError::UnsupportedCompression(compression_type.to_raw()));
+        }
+    }
+
+    Ok(decompressed_data)
+}
+
+/// Spawns the background thread that reads and decompresses queued blocks into the shared cache.
+/// The thread exits once the owning `FseqFile` (and with it the job sender) is dropped.
+fn spawn_prefetch_worker(storage: Arc<FseqStorage>, block_cache: Arc<SharedBlockCache>, compression_type: CompressionType) -> Sender<PrefetchJob> {
+    let (tx, rx) = channel::<PrefetchJob>();
+
+    let spawn_result = thread::Builder::new()
+        .name("fseq-prefetch".to_string())
+        .spawn(move || {
+            for job in rx {
+                // Out-of-bounds blocks are left for get_frame to report when they are actually needed.
+                let result = read_compressed_block(&storage, job.block_index, job.block)
+                    .and_then(|compressed_data| decompress_block(compression_type, &compressed_data));
+                let mut cache = block_cache.lock();
+
+                match result {
+                    Ok(decompressed_data) => {
+                        if !cache.insert_prefetched(job.block_index, Arc::new(decompressed_data)) {
+                            log::debug!("Prefetched block {} does not fit in the block cache beside the block being played.", job.block_index);
+                        }
+                    }
+                    Err(e) => {
+                        log::warn!("Prefetch of block {} failed: {}", job.block_index, e);
+                        cache.insert_failure(job.block_index, &e);
+                    }
+                }
+                drop(cache);
+                block_cache.notify_resolved();
+            }
+        });
+
+    if let Err(e) = spawn_result {
+        log::error!("Failed to spawn FSEQ prefetch thread: {}", e);
+    }
+
+    tx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_data::{self, V2Fixture};
+
+    #[test]
+    fn decodes_zlib_blocks() {
+        let buffer = V2Fixture::new(CompressionType::Zlib, 300, 50, 64).build();
+        let file = FseqFile::parse(buffer.clone()).unwrap();
+
+        assert_eq!(file.header.compression_type, CompressionType::Zlib);
+        assert_eq!(file.get_compression_blocks().len(), 5);
+        for frame in 0..file.get_frame_count() {
+            assert_eq!(file.get_frame(frame).unwrap(), test_data::frame_data(frame, 50), "frame {} differs", frame);
+        }
+
+        // A corrupt ZLIB block fails on its own frames only.
+        let second_block = file.get_compression_blocks()[1];
+        let mut corrupt = buffer;
+        let block_offset = second_block.offset as usize;
+        corrupt[block_offset..block_offset + 4].fill(0xFF);
+        let corrupt = FseqFile::parse(corrupt).unwrap();
+        assert!(matches!(corrupt.get_frame(70), Err(FseqError::DecompressFailed { compression: CompressionType::Zlib, .. })));
+        assert_eq!(corrupt.get_frame(140).unwrap(), test_data::frame_data(140, 50));
+    }
+
+    #[test]
+    fn caches_decode_failures() {
+        let buffer = V2Fixture::new(CompressionType::Zstd, 300, 40, 100).build();
+        let file = FseqFile::parse(buffer.clone()).unwrap().with_prefetch(false);
+        let block_offset = file.get_compression_blocks()[1].offset as usize;
+
+        let mut corrupt = buffer.clone();
+        corrupt[block_offset..block_offset + 8].fill(0xAA);
+        let corrupt = FseqFile::parse(corrupt).unwrap().with_prefetch(false);
+        assert!(matches!(corrupt.get_frame(100), Err(FseqError::DecompressFailed { .. })));
+        assert!(corrupt.lock_block_cache().failure(1).is_some());
+        // Later frames of the block fail from the cache, with the same error.
+        assert!(matches!(corrupt.get_frame(150), Err(FseqError::DecompressFailed { .. })));
+        assert!(corrupt.get_frame(250).is_ok());
+
+        let single_stream = V2Fixture::new(CompressionType::Zlib, 50, 10, 0).build();
+        let mut corrupt = single_stream.clone();
+        corrupt[single_stream.len() - 40..].fill(0xAA);
+        let corrupt = FseqFile::parse(corrupt).unwrap();
+        assert!(corrupt.get_frame(49).is_err());
+        assert!(matches!(corrupt.single_stream_data.get(), Some(Err(_))));
+        assert!(corrupt.get_frame(0).is_err());
+
+        // Stream reads that fail with an I/O error are skipped, and retried next time.
+        let io_error = FseqError::Io(std::io::Error::other("device unplugged"));
+        assert!(io_error.is_recoverable());
+        assert!(io_error.cached_copy().is_none());
+    }
+
+    #[test]
+    fn decodes_single_stream_files() {
+        for compression_type in [CompressionType::Zstd, CompressionType::Zlib] {
+            let buffer = V2Fixture::new(compression_type, 200, 30, 0).build();
+            let file = FseqFile::parse(buffer.clone()).unwrap();
+
+            assert_eq!(file.header.compression_block_count, 0);
+            assert!(file.get_compression_blocks().is_empty());
+            assert!(file.single_stream_data.get().is_none());
+
+            for frame in (0..file.get_frame_count()).rev() {
+                assert_eq!(file.get_frame(frame).unwrap(), test_data::frame_data(frame, 30), "{}: frame {} differs", compression_type, frame);
+            }
+            assert_eq!(file.single_stream_data.get().and_then(|result| result.as_ref().ok()).map(Vec::len), Some(200 * 30));
+
+            let truncated = FseqFile::parse(buffer[..buffer.len() - 8].to_vec()).unwrap();
+            assert!(truncated.get_frame(199).is_err());
+        }
+    }
+
+    #[test]
+    fn looks_up_blocks_by_frame() {
+        let mut fixture = V2Fixture::new(CompressionType::Zstd, 600, 20, 256);
+        fixture.small_first_block = true;
+        fixture.padding_blocks = 3;
+        let file = FseqFile::parse(fixture.build()).unwrap();
+
+        // Blocks start at frames 0, 10, 266 and 522; the padding entries are skipped.
+        assert_eq!(file.get_compression_blocks().len(), 4);
+        for (frame, block_index) in [(0, 0), (9, 0), (10, 1), (265, 1), (266, 2), (521, 2), (522, 3), (599, 3)] {
+            assert_eq!(file.find_block_index(frame).unwrap(), block_index, "frame {}", frame);
+            assert_eq!(file.get_frame(frame).unwrap(), test_data::frame_data(frame, 20), "frame {} differs", frame);
+        }
+    }
+
+    #[test]
+    fn caches_decompressed_blocks() {
+        let file = FseqFile::parse(V2Fixture::new(CompressionType::Zstd, 300, 40, 100).build()).unwrap()
+            .with_prefetch(false)
+            .with_block_cache_budget(100 * 40);
+
+        let first = file.get_block_data(0).unwrap();
+        assert!(Arc::ptr_eq(&first, &file.get_block_data(0).unwrap()));
+
+        // The budget holds one block, so loading the next evicts the first.
+        file.get_frame(150).unwrap();
+        assert!(file.lock_block_cache().contains(1));
+        assert!(!file.lock_block_cache().contains(0));
+        assert!(!Arc::ptr_eq(&first, &file.get_block_data(0).unwrap()));
+    }
+
+    #[test]
+    fn prefetch_keeps_the_block_being_played_in_a_one_block_budget() {
+        let file = FseqFile::parse(V2Fixture::new(CompressionType::Zstd, 300, 40, 100).build()).unwrap()
+            .with_block_cache_budget(100 * 40);
+
+        let first = file.get_block_data(0).unwrap();
+        // Block 1 is queued for prefetch; wait until the worker is done with it.
+        file.prefetch_block(1);
+        drop(file.block_cache.wait_for(file.lock_block_cache(), 1));
+
+        // The prefetched block did not fit and was dropped; block 0 is still cached.
+        assert!(!file.lock_block_cache().contains(1));
+        assert!(Arc::ptr_eq(&first, &file.get_block_data(0).unwrap()));
+        assert_eq!(file.get_frame(99).unwrap(), test_data::frame_data(99, 40));
+    }
+
+    #[test]
+    fn waits_for_blocks_being_prefetched() {
+        let file = FseqFile::parse(V2Fixture::new(CompressionType::Zstd, 300, 40, 100).build()).unwrap();
+
+        // Reading a pending block returns the worker's copy instead of decoding it again.
+        file.prefetch_block(2);
+        let data = file.get_block_data(2).unwrap();
+        assert!(!file.lock_block_cache().is_pending(2));
+        assert!(Arc::ptr_eq(&data, &file.get_block_data(2).unwrap()));
+        assert_eq!(file.get_frame(250).unwrap(), test_data::frame_data(250, 40));
+    }
+
+    #[test]
+    fn prefetches_streamed_blocks_in_the_background() {
+        let path = std::env::temp_dir().join(format!("fseq-prefetch-{}.fseq", std::process::id()));
+        std::fs::write(&path, V2Fixture::new(CompressionType::Zstd, 300, 40, 100).build()).unwrap();
+        let file = FseqFile::open(&path, LoadMode::Stream).unwrap();
+
+        file.get_frame(0).unwrap();
+        let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+        while !file.lock_block_cache().contains(1) {
+            assert!(std::time::Instant::now() < deadline, "block 1 was never prefetched");
+            thread::sleep(std::time::Duration::from_millis(5));
+        }
+        assert_eq!(file.get_frame(150).unwrap(), test_data::frame_data(150, 40));
+
+        drop(file);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn places_sparse_ranges_at_their_channels() {
+        let mut fixture = V2Fixture::new(CompressionType::None, 30, 25, 0);
+        fixture.sparse_ranges = vec![(512, 10), (40, 15)];
+        let buffer = fixture.build();
+        let file = FseqFile::parse(buffer.clone()).unwrap();
+
+        let frame = file.get_frame(3).unwrap();
+        let segments: Vec<(u32, &[u8])> = file.frame_segments(&frame)
+            .map(|segment| (segment.start_channel, segment.data))
+            .collect();
+        assert_eq!(segments, vec![(512, &frame[..10]), (40, &frame[10..])]);
+
+        // A short frame yields only the ranges it reaches.
+        let partial: Vec<(u32, usize)> = file.frame_segments(&frame[..6])
+            .map(|segment| (segment.start_channel, segment.data.len()))
+            .collect();
+        assert_eq!(partial, vec![(512, 6)]);
+
+        // Dense files are a single segment at channel 0.
+        let dense = FseqFile::parse(V2Fixture::new(CompressionType::None, 2, 25, 0).build()).unwrap();
+        assert_eq!(dense.frame_segments(&frame).map(|segment| segment.start_channel).collect::<Vec<_>>(), vec![0]);
+
+        // Ranges covering more channels than a frame holds are rejected.
+        let mut oversized = buffer;
+        oversized[10..14].copy_from_slice(&20u32.to_le_bytes());
+        assert!(matches!(FseqFile::parse(oversized), Err(FseqError::InvalidHeader(_))));
+    }
+
+    #[test]
+    fn decodes_variable_headers() {
+        let media_filename = VariableHeader::from_raw(*b"mf", b"show.mp3\0\0\0");
+        assert_eq!(media_filename, VariableHeader::MediaFilename("show.mp3".to_string()));
+        assert_eq!(media_filename.to_raw_value(), b"show.mp3\0");
+        assert_eq!(media_filename.to_string(), "mf: show.mp3");
+
+        let unknown = VariableHeader::from_raw(*b"FC", &[0, 7]);
+        assert_eq!(unknown, VariableHeader::Unknown { code: *b"FC", data: vec![0, 7] });
+        assert_eq!(unknown.to_raw_value(), vec![0, 7]);
+        assert_eq!(unknown.to_string(), "FC: 2 bytes [00, 07]");
+
+        // The first entry's length runs past the channel data.
+        let mut buffer = V2Fixture::new(CompressionType::None, 2, 3, 0).build();
+        let variable_data_offset = u16::from_le_bytes([buffer[8], buffer[9]]) as usize;
+        buffer[variable_data_offset..variable_data_offset + 2].copy_from_slice(&0x0400u16.to_le_bytes());
+        assert!(matches!(FseqFile::parse(buffer), Err(FseqError::InvalidHeader(_))));
+    }
+
+    #[test]
+    fn reads_v1_headers() {
+        let buffer = test_data::build_v1(40, 21, b"PSEQ");
+        let file = FseqFile::parse(buffer.clone()).unwrap();
+
+        assert_eq!(file.get_version(), (1, 0));
+        assert_eq!(file.header.variable_data_offset as usize, V1_FIXED_HEADER_SIZE);
+        assert_eq!((file.get_frame_count(), file.get_channel_count()), (40, 21));
+        assert_eq!(file.header.compression_type, CompressionType::None);
+        assert!(file.get_compression_blocks().is_empty() && !file.is_sparse());
+        assert_eq!(file.get_media_filename(), Some(test_data::MEDIA_FILENAME));
+        assert_eq!(file.get_frame(39).unwrap(), test_data::frame_data(39, 21));
+
+        // Step times are 16-bit in v1 but must fit the v2 header's single byte.
+        for step_time_ms in [0u16, 300] {
+            let mut bad_step = buffer.clone();
+            bad_step[18..20].copy_from_slice(&step_time_ms.to_le_bytes());
+            assert!(matches!(FseqFile::parse(bad_step), Err(FseqError::InvalidHeader(_))), "step time {}", step_time_ms);
+        }
+
+        let mut bad_offsets = buffer;
+        bad_offsets[8..10].copy_from_slice(&20u16.to_le_bytes());
+        assert!(matches!(FseqFile::parse(bad_offsets), Err(FseqError::InvalidHeader(_))));
+    }
+}
@@ -0,0 +1,162 @@
+// src/fseq_error.rs
+use serde::Serialize;
+use std::io;
+use std::path::PathBuf;
+use thiserror::Error;
+use crate::fseq::CompressionType;
+
+pub type FseqResult<T> = std::result::Result<T, FseqError>;
+
+// ----------------------------------------------------------------------
+// FseqError Enum
+// ----------------------------------------------------------------------
+/// Errors raised while opening, parsing and decoding FSEQ files.
+#[derive(Debug, Error)]
+pub enum FseqError {
+    #[error("failed to open FSEQ file at {}: {source}", path.display())]
+    Open { path: PathBuf, source: io::Error },
+
+    #[error("FSEQ I/O error: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("FSEQ data is {size} bytes, too small for a valid header (must be >= {required} bytes)")]
+    HeaderTooSmall { size: usize, required: usize },
+
+    #[error("invalid FSEQ magic {:?}", String::from_utf8_lossy(.0))]
+    InvalidMagic([u8; 4]),
+
+    #[error("unsupported FSEQ compression type {0}")]
+    UnsupportedCompression(u8),
+
+    /// A header field, block table, sparse range table or variable header is malformed.
+    #[error("invalid FSEQ header: {0}")]
+    InvalidHeader(String),
+
+    #[error("frame {frame} is out of range (the sequence has {frame_count} frames)")]
+    FrameOutOfRange { frame: u32, frame_count: u32 },
+
+    #[error("frame buffer is {actual} bytes, but frames are {expected} bytes")]
+    FrameBufferSize { expected: usize, actual: usize },
+
+    #[error("bytes {start}-{end} are outside the file ({file_size} bytes); the file is likely truncated")]
+    Truncated { start: u64, end: u64, file_size: u64 },
+
+    #[error("compression block {block} (bytes {start}-{end}) extends past the end of the file ({file_size} bytes)")]
+    TruncatedBlock { block: usize, start: u64, end: u64, file_size: u64 },
+
+    #[error("failed to decompress {compression} frame data: {source}")]
+    DecompressFailed { compression: CompressionType, source: io::Error },
+
+    #[error("{size} bytes of frame data do not fit in memory on this platform")]
+    DataTooLarge { size: u64 },
+
+    #[error("decompressed data is {available} bytes, too small for frame {frame} (needs {required})")]
+    DecodedSizeMismatch { frame: u32, available: usize, required: usize },
+}
+
+impl FseqError {
+    /// Whether the error is confined to the requested frame's data, so playback can skip the
+    /// frame and continue. Other errors mean the file or the call itself is unusable.
+    /// I/O errors count as recoverable: after the header is parsed they only come from reading
+    /// a frame or block of a streamed file, which a later read may well get past.
+    pub fn is_recoverable(&self) -> bool {
+        matches!(
+            self,
+            FseqError::Io(_)
+                | FseqError::Truncated { .. }
+                | FseqError::TruncatedBlock { .. }
+                | FseqError::DecompressFailed { .. }
+                | FseqError::DataTooLarge { .. }
+                | FseqError::DecodedSizeMismatch { .. }
+        )
+    }
+
+    /// A copy of a decode failure to cache for its block, so the block is not read and
+    /// decompressed again on every frame. `None` for errors a retry could get past, such as I/O
+    /// errors, and for errors that are not about the block's data.
+    pub fn cached_copy(&self) -> Option<FseqError> {
+        match self {
+            FseqError::Truncated { start, end, file_size } => Some(FseqError::Truncated { start: *start, end: *end, file_size: *file_size }),
+            FseqError::TruncatedBlock { block, start, end, file_size } => {
+                Some(FseqError::TruncatedBlock { block: *block, start: *start, end: *end, file_size: *file_size })
+            }
+            FseqError::DecompressFailed { compression, source } => Some(FseqError::DecompressFailed {
+                compression: *compression,
+                source: io::Error::new(source.kind(), source.to_string()),
+            }),
+            FseqError::DataTooLarge { size } => Some(FseqError::DataTooLarge { size: *size }),
+            _ => None,
+        }
+    }
+}
+
+// ----------------------------------------------------------------------
+// Validation Report Structs
+// ----------------------------------------------------------------------
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// Playback would fail or send wrong data.
+    Error,
+    /// Unusual but playable, e.g. bytes after the channel data.
+    Warning,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IssueKind {
+    /// Channel data or a compression block ends past the end of the file.
+    Truncated,
+    /// A header offset points outside the file.
+    BadOffset,
+    /// The header's step time is 0ms, so the sequence has no frame rate.
+    BadStepTime,
+    /// Decoded data does not match the size declared by the header.
+    SizeMismatch,
+    /// A compressed block or stream could not be decompressed.
+    DecodeFailed,
+    /// One or more frames could not be read.
+    FrameFailed,
+    /// Extra bytes after the channel data.
+    TrailingData,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ValidationIssue {
+    pub severity: Severity,
+    pub kind: IssueKind,
+    pub message: String,
+}
+
+impl ValidationIssue {
+    pub fn error(kind: IssueKind, message: String) -> Self {
+        ValidationIssue { severity: Severity::Error, kind, message }
+    }
+
+    pub fn warning(kind: IssueKind, message: String) -> Self {
+        ValidationIssue { severity: Severity::Warning, kind, message }
+    }
+
+    /// Reports a run of consecutive frames that failed with the same first error.
+    pub fn frames_failed(first_frame: u32, last_frame: u32, error: &str) -> Self {
+        let frames = if first_frame == last_frame {
+            format!("Frame {}", first_frame)
+        } else {
+            format!("Frames {}-{}", first_frame, last_frame)
+        };
+        Self::error(IssueKind::FrameFailed, format!("{} failed to decode: {}", frames, error))
+    }
+}
+
+/// Result of `FseqFile::validate`: every problem found in the file, rather than the first error.
+#[derive(Debug, Clone, Serialize)]
+pub struct ValidationReport {
+    pub frames_decoded: u32,
+    pub issues: Vec<ValidationIssue>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.issues.iter().all(|issue| issue.severity != Severity::Error)
+    }
+}
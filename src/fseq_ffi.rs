@@ -4,256 +4,187 @@
 #![allow(non_snake_case)]
 
 use std::ptr;
-use anyhow::{Result, bail, Context};
-use std::io::Read;
-use zstd::stream::Decoder as ZstdDecoder;
 use std::os::raw::{c_char, c_int};
 use std::ffi::CStr;
-use std::convert::TryInto; 
+use crate::fseq_error::{FseqError, FseqResult};
+use crate::fseq::{
+    ChannelRange, CompressionType, FseqHeader, FseqParser, RawVariableHeader,
+    BLOCK_INDEX_ENTRY_SIZE, CHANNEL_RANGE_ENTRY_SIZE, FIXED_HEADER_SIZE, VARIABLE_HEADER_PREFIX_SIZE,
+};
 
-include!(concat!(env!("OUT_DIR"), "/tinyfseq_bindings.rs")); 
+include!(concat!(env!("OUT_DIR"), "/tinyfseq_bindings.rs"));
 
 // ----------------------------------------------------------------------
-// FseqFile Struct
+// TinyFseqParser Struct
 // ----------------------------------------------------------------------
-/// Represents a parsed FSEQ file, holding the raw data and header information.
-pub struct FseqFile {
-    pub buffer: Vec<u8>,
-    pub header: tf_header_t, 
+/// FSEQ v2 parser backed by the libtinyfseq C library.
+pub struct TinyFseqParser;
+
+/// Converts a tinyfseq error code into a readable message.
+fn error_message(result: TFError) -> String {
+    let error_string_ptr = unsafe { TFError_string(result) };
+    if error_string_ptr.is_null() {
+        format!("error code {}", result)
+    } else {
+        let c_str = unsafe { CStr::from_ptr(error_string_ptr as *const c_char) };
+        c_str.to_string_lossy().into_owned()
+    }
 }
 
-impl FseqFile {
-    /// Parses the raw buffer to initialize the file's header.
-    pub fn parse(buffer: Vec<u8>) -> Result<Self> {
-        
-        const MIN_HEADER_SIZE: usize = 32;
-        if buffer.len() < MIN_HEADER_SIZE {
-            bail!("FSEQ buffer size is too small for a valid header (must be >= 32 bytes).");
+impl FseqParser for TinyFseqParser {
+    fn read_header(buffer: &[u8]) -> FseqResult<FseqHeader> {
+        if buffer.len() < FIXED_HEADER_SIZE {
+            return Err(FseqError::HeaderTooSmall { size: buffer.len(), required: FIXED_HEADER_SIZE });
         }
 
         let mut header: tf_header_t = unsafe { std::mem::zeroed() };
-        
+
         let result = unsafe {
             TFHeader_read(
-                buffer.as_ptr(), 
-                buffer.len() as c_int, 
-                &mut header, 
+                buffer.as_ptr(),
+                buffer.len() as c_int,
+                &mut header,
                 ptr::null_mut()
             )
         };
 
         if result != tf_err_t_TF_OK {
-            let error_string_ptr = unsafe { TFError_string(result) };
-            let error_message = if error_string_ptr.is_null() {
-                format!("Failed to parse FSEQ header with error code: {}", result)
-            } else {
-                let c_str = unsafe { CStr::from_ptr(error_string_ptr as *const c_char) };
-                format!("FSEQ header read error: {}", c_str.to_string_lossy())
-            };
-            bail!(error_message);
+            return Err(FseqError::InvalidHeader(format!("tinyfseq: {}", error_message(result))));
         }
 
-        let fseq_file = FseqFile {
-            buffer,
-            header,
+        let compression_type = match header.compressionType {
+            tf_compression_type_t_TF_COMPRESSION_NONE => CompressionType::None,
+            tf_compression_type_t_TF_COMPRESSION_ZSTD => CompressionType::Zstd,
+            tf_compression_type_t_TF_COMPRESSION_ZLIB => CompressionType::Zlib,
+            other => return Err(FseqError::UnsupportedCompression(other as u8)),
         };
 
-        Ok(fseq_file)
-    }
-    
-    /// Prints a summary of the FSEQ header contents for debugging.
-    pub fn dump_header_info(&self) {
-        
-        let raw_byte_20 = self.buffer[20];
-        let raw_byte_21 = self.buffer[21];
-        
-        let ecbc_upper_4_bits = (raw_byte_20 & 0xF0) as u16;
-        let ecbc_lower_8_bits = raw_byte_21 as u16;
-        let extended_compression_block_count: u16 = (ecbc_upper_4_bits << 4) | ecbc_lower_8_bits;
-
-        println!("\n--- FSEQ Header Dump ---");
-        
-        println!("Channel Data Offset (Byte 4-5): {}", self.header.channelDataOffset);
-        println!("Variable Data Offset (Header Size, Byte 8-9): {}", self.header.variableDataOffset);
-        
-        println!("Version: {}.{}", self.header.majorVersion, self.header.minorVersion);
-        println!("Frames: {}", self.header.frameCount);
-        println!("Channels: {}", self.header.channelCount);
-        println!("Step Time Ms: {}", self.header.frameStepTimeMillis);
-        
-        println!("--- Compression Data ---");
-        println!("Raw Byte 20 (Type + ECBC): 0x{:X}", raw_byte_20);
-        println!("Raw Byte 21 (Block Count): {}", raw_byte_21);
-        println!("FFI-Parsed Compression Type: {} ({} & 0x0F)", self.header.compressionType, raw_byte_20);
-        println!("FFI-Parsed Block Count (Byte 21): {}", self.header.compressionBlockCount);
-        println!("Sparse Range Count (Byte 22): {}", self.header.channelRangeCount);
-        println!("Calculated Block Count (ECBC 12-bit): {}", extended_compression_block_count);
-        
-        println!("------------------------\n");
+        // tinyfseq only reads byte 21; the upper nibble of byte 20 extends the block count to 12 bits.
+        let compression_block_count = (((buffer[20] & 0xF0) as u16) << 4) | header.compressionBlockCount as u16;
+
+        Ok(FseqHeader {
+            channel_data_offset: header.channelDataOffset,
+            minor_version: header.minorVersion,
+            major_version: header.majorVersion,
+            variable_data_offset: header.variableDataOffset,
+            channel_count: header.channelCount,
+            frame_count: header.frameCount,
+            step_time_ms: header.frameStepTimeMillis,
+            compression_type,
+            compression_block_count,
+            channel_range_count: header.channelRangeCount,
+            sequence_uid: header.sequenceUid,
+        })
     }
 
-    /// Retrieves the frame data. Handles ZLIB/ZSTD decompression if needed.
-    pub fn get_frame(&self, frame_num: u32) -> Result<Vec<u8>> {
-        if frame_num >= self.header.frameCount {
-            bail!("Frame number {} is out of bounds (total frames: {})", frame_num, self.header.frameCount);
-        }
-
-        let channel_count = self.header.channelCount as usize;
-        let frame_size = channel_count; 
-        
-        match self.header.compressionType { 
-            tf_compression_type_t_TF_COMPRESSION_NONE => {
-                // UNCOMPRESSED LOGIC: Frames are stored contiguously starting at channelDataOffset.
-                
-                let data_offset = self.header.channelDataOffset as usize;
-                let frame_offset = frame_num as usize * frame_size;
-                
-                let frame_start = data_offset + frame_offset;
-                let frame_end = frame_start + frame_size;
-                
-                if frame_end > self.buffer.len() {
-                    bail!("Uncompressed frame boundaries ({}-{}) are outside the file buffer (size: {}). File is likely truncated.", 
-                          frame_start, frame_end, self.buffer.len());
-                }
-
-                // Slice the raw data from the buffer
-                return Ok(self.buffer[frame_start..frame_end].to_vec());
-            }
-            tf_compression_type_t_TF_COMPRESSION_ZSTD => {
-                // ZSTD DECOMPRESSION LOGIC
-                
-                // Calculate ECBC
-                let raw_byte_20 = self.buffer[20];
-                let raw_byte_21 = self.buffer[21];
-                let ecbc_upper_4_bits = (raw_byte_20 & 0xF0) as u16;
-                let ecbc_lower_8_bits = raw_byte_21 as u16;
-                let extended_compression_block_count: u16 = (ecbc_upper_4_bits << 4) | ecbc_lower_8_bits;
-                let block_count = extended_compression_block_count as usize;
-                
-                let frames_per_block: u32 = 256; 
-                let compressed_data_slice: &[u8];
-
-                if block_count == 0 {
-                    bail!("Compressed FSEQ file with block count 0 is unsupported (Single-stream ZSTD).");
-                }
-
-                // Block Indexing Fix (Handling Block 0 10-frame exception)
-                let block_index: usize;
-                let frame_in_block: u32;
-
-                let mut current_frame_num: u32 = 0;
-                let mut current_block_index: usize = 0;
-                const BLOCK0_SIZE_FRAMES: u32 = 10;
+    fn read_compression_block(entry: &[u8]) -> FseqResult<(u32, u32)> {
+        let mut block: tf_compression_block_t = unsafe { std::mem::zeroed() };
 
-                loop {
-                    let frames_in_current_block = if current_block_index == 0 { 
-                        BLOCK0_SIZE_FRAMES
-                    } else { 
-                        frames_per_block 
-                    };
-
-                    if frame_num < current_frame_num + frames_in_current_block {
-                        block_index = current_block_index;
-                        frame_in_block = frame_num - current_frame_num;
-                        eprintln!("DEBUG: Block indexing fix applied: Block index={}, Frame in block={}", block_index, frame_in_block);
-                        break;
-                    }
-
-                    current_frame_num += frames_in_current_block;
-                    current_block_index += 1;
-
-                    if current_block_index >= block_count {
-                        bail!("Frame number {} is outside the available blocks.", frame_num);
-                    }
-                }
-                
-                // --- Block Metadata Reading ---
-                let block_metadata_size = block_count * 8;
-                let compressed_data_section_start = self.header.channelDataOffset as usize;
-                let block_metadata_table_start = self.header.variableDataOffset as usize - block_metadata_size;
-                let block_metadata_read_start = block_metadata_table_start + (block_index * 8); 
-                
-                eprintln!("DEBUG: Calculated Block Count (ECBC): {}", block_count);
-                eprintln!("DEBUG: Block Metadata Size: {} bytes", block_metadata_size);
-                eprintln!("DEBUG: Compressed Data Section Start (Channel Data Offset): {}", compressed_data_section_start);
-                eprintln!("DEBUG: Block {} metadata read start index: {}", block_index, block_metadata_read_start);
-
-                if block_metadata_read_start + 8 > self.buffer.len() {
-                     bail!("File buffer too small to read block metadata (Expected 8 bytes at offset {}).", block_metadata_read_start);
-                }
-                
-                let block_data_slice = &self.buffer[block_metadata_read_start..block_metadata_read_start + 8];
-                eprintln!("DEBUG: Raw 8 bytes for block metadata: {:?}", block_data_slice);
+        let result = unsafe {
+            TFCompressionBlock_read(
+                entry.as_ptr(),
+                entry.len().min(BLOCK_INDEX_ENTRY_SIZE) as c_int,
+                &mut block,
+                ptr::null_mut()
+            )
+        };
 
-                let first_frame_id = u32::from_le_bytes(block_data_slice[0..4].try_into().unwrap());
-                let size = u32::from_le_bytes(block_data_slice[4..8].try_into().unwrap());
-                
-                eprintln!("DEBUG: Converted values: firstFrameId={}, size={}", first_frame_id, size);
+        if result != tf_err_t_TF_OK {
+            return Err(FseqError::InvalidHeader(format!("tinyfseq compression block: {}", error_message(result))));
+        }
 
-                
-                let mut compressed_chunk_start = compressed_data_section_start + first_frame_id as usize; 
-                let mut compressed_chunk_end = compressed_chunk_start + size as usize;
-                
-                // Corruption workaround remains for Block 0, using the size from metadata
-                if compressed_chunk_start > self.buffer.len() && block_index == 0 {
-                    eprintln!("DEBUG: CORRUPTION WORKAROUND TRIGGERED. Bad offset: {}. Forcing Block 0 start.", compressed_chunk_start);
-                    
-                    compressed_chunk_start = compressed_data_section_start; 
-                    compressed_chunk_end = compressed_chunk_start + size as usize; 
-                    
-                    eprintln!("DEBUG: CORRUPTION FIX APPLIED: ChunkStart forced to {}, ChunkEnd set to {} (End of file: {}).", 
-                              compressed_chunk_start, compressed_chunk_end, self.buffer.len());
-                }
+        Ok((block.firstFrameId, block.size))
+    }
 
+    fn read_channel_range(entry: &[u8]) -> FseqResult<ChannelRange> {
+        let mut channel_range: tf_channel_range_t = unsafe { std::mem::zeroed() };
 
-                eprintln!("DEBUG: Calculated boundaries (final): ChunkStart={}, ChunkEnd={}, FileSize={}",
-                          compressed_chunk_start, compressed_chunk_end, self.buffer.len());
+        let result = unsafe {
+            TFChannelRange_read(
+                entry.as_ptr(),
+                entry.len().min(CHANNEL_RANGE_ENTRY_SIZE) as c_int,
+                &mut channel_range,
+                ptr::null_mut()
+            )
+        };
 
+        if result != tf_err_t_TF_OK {
+            return Err(FseqError::InvalidHeader(format!("tinyfseq sparse channel range: {}", error_message(result))));
+        }
 
-                if compressed_chunk_end > self.buffer.len() {
-                    bail!("Compressed data chunk boundaries ({}-{}) are outside the file buffer (size: {}). This indicates incorrect offset/size values in the file metadata.", 
-                          compressed_chunk_start, compressed_chunk_end, self.buffer.len());
-                }
+        Ok(ChannelRange {
+            start_channel: channel_range.firstChannelNumber,
+            channel_count: channel_range.channelCount,
+        })
+    }
 
-                compressed_data_slice = &self.buffer[compressed_chunk_start..compressed_chunk_end];
-                eprintln!("DEBUG: ZSTD Header Skip Reverted: Passing chunk of size {} bytes to decoder.", compressed_data_slice.len());
+    fn read_variable_header(data: &[u8]) -> FseqResult<RawVariableHeader> {
+        if data.len() < VARIABLE_HEADER_PREFIX_SIZE {
+            return Err(FseqError::InvalidHeader(format!("variable header needs {} bytes, only {} available", VARIABLE_HEADER_PREFIX_SIZE, data.len())));
+        }
 
+        // Size the value buffer from the declared length so tinyfseq can copy the whole value.
+        let declared_size = u16::from_le_bytes([data[0], data[1]]) as usize;
+        let mut var_header: tf_var_header_t = unsafe { std::mem::zeroed() };
+        let mut value = vec![0u8; declared_size.saturating_sub(VARIABLE_HEADER_PREFIX_SIZE)];
 
-                // --- ZSTD Decompression & Extraction Steps ---
-                let mut decoder = ZstdDecoder::new(compressed_data_slice).context("Failed to create ZSTD decoder.")?;
-                let mut decompressed_data = Vec::new();
-                
-                decoder.read_to_end(&mut decompressed_data).context("Failed to decompress ZSTD frame data.")?;
+        let result = unsafe {
+            TFVarHeader_read(
+                data.as_ptr(),
+                data.len() as c_int,
+                &mut var_header,
+                value.as_mut_ptr(),
+                value.len() as c_int,
+                ptr::null_mut()
+            )
+        };
 
-                let frame_start = frame_in_block as usize * frame_size;
-                let frame_end = frame_start + frame_size;
+        if result != tf_err_t_TF_OK {
+            return Err(FseqError::InvalidHeader(format!("tinyfseq variable header: {}", error_message(result))));
+        }
 
-                if frame_end > decompressed_data.len() {
-                    bail!("Decompressed data ({} bytes) is too small to contain the requested frame (starts at {}).", decompressed_data.len(), frame_start);
-                }
+        Ok(RawVariableHeader {
+            size: var_header.size,
+            code: var_header.id,
+            data: value,
+        })
+    }
+}
 
-                return Ok(decompressed_data[frame_start..frame_end].to_vec());
-            }
-            tf_compression_type_t_TF_COMPRESSION_ZLIB => {
-                // ZLIB DECOMPRESSION LOGIC (Still requires the same indexing and structural fixes. Please focus on ZSTD/None first.)
-                bail!("ZLIB compression logic requires implementation.");
-            }
-            _ => {
-                bail!("Unknown FSEQ compression type: {}", self.header.compressionType);
+#[cfg(all(test, feature = "native"))]
+mod tests {
+    use super::*;
+    use crate::fseq::FseqFile;
+    use crate::fseq_native::NativeParser;
+    use crate::test_data;
+
+    /// Both backends must produce identical `FseqFile` results for every fixture layout.
+    #[test]
+    fn cross_validates_with_native_parser() {
+        for (name, buffer) in test_data::v2_fixtures() {
+            let native = FseqFile::parse_with::<NativeParser>(buffer.clone()).unwrap();
+            let tinyfseq = FseqFile::parse_with::<TinyFseqParser>(buffer).unwrap();
+
+            assert_eq!(native.header, tinyfseq.header, "{}: header differs", name);
+            assert_eq!(native.get_compression_blocks(), tinyfseq.get_compression_blocks(), "{}: block index differs", name);
+            assert_eq!(native.get_channel_ranges(), tinyfseq.get_channel_ranges(), "{}: sparse ranges differ", name);
+            assert_eq!(native.variable_headers(), tinyfseq.variable_headers(), "{}: variable headers differ", name);
+
+            for frame in 0..native.get_frame_count() {
+                assert_eq!(native.get_frame(frame).unwrap(), tinyfseq.get_frame(frame).unwrap(), "{}: frame {} differs", name, frame);
             }
         }
     }
-    
-    // Getters
-    pub fn get_step_time(&self) -> i32 {
-        self.header.frameStepTimeMillis as i32
-    }
 
-    pub fn get_channel_count(&self) -> u32 {
-        self.header.channelCount
-    }
+    #[test]
+    fn cross_validates_entry_readers() {
+        let block_entry = [0x0A, 0x00, 0x00, 0x00, 0x34, 0x12, 0x00, 0x00];
+        assert_eq!(NativeParser::read_compression_block(&block_entry).unwrap(), TinyFseqParser::read_compression_block(&block_entry).unwrap());
+
+        let range_entry = [0x10, 0x27, 0x00, 0x00, 0x02, 0x01];
+        assert_eq!(NativeParser::read_channel_range(&range_entry).unwrap(), TinyFseqParser::read_channel_range(&range_entry).unwrap());
 
-    pub fn get_frame_count(&self) -> u32 {
-        self.header.frameCount
+        let var_header = [0x07, 0x00, b's', b'p', b'x', b'L', 0x00];
+        assert_eq!(NativeParser::read_variable_header(&var_header).unwrap(), TinyFseqParser::read_variable_header(&var_header).unwrap());
     }
 }
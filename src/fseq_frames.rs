@@ -0,0 +1,209 @@
+// src/fseq_frames.rs
+use std::future::Future;
+use std::io;
+use std::ops::Range;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, ready};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tokio::time::{Sleep, sleep_until};
+use tokio_stream::Stream;
+use crate::frame_clock::FrameClock;
+use crate::fseq::FseqFile;
+use crate::fseq_error::{FseqError, FseqResult};
+
+impl FseqFile {
+    /// Iterates over every frame in order.
+    pub fn frames(&self) -> Frames<'_> {
+        self.frames_range(0..self.get_frame_count())
+    }
+
+    /// Iterates over the frames in `range`; the end is clamped to the frame count.
+    pub fn frames_range(&self, range: Range<u32>) -> Frames<'_> {
+        let end = range.end.min(self.get_frame_count());
+        Frames { fseq_file: self, next: range.start.min(end), end }
+    }
+
+    /// Streams the frames in `range` as `(timestamp, frame)` pairs, paced by the step time.
+    ///
+    /// Timestamps are offsets from the start of the sequence. The first frame is yielded
+    /// immediately and each following frame at its deadline from that start, so slow
+    /// consumers do not accumulate drift. A consumer that stalls for more than a step gets
+    /// the frame due now next, not every frame it missed. Frames are decoded on the blocking
+    /// thread pool, so reads and decompression never stall the runtime.
+    pub fn frame_stream(self: Arc<Self>, range: Range<u32>) -> FrameStream {
+        let end = range.end.min(self.get_frame_count());
+        let first = range.start.min(end);
+        let start = Instant::now();
+        FrameStream {
+            clock: FrameClock::new(Duration::from_millis(self.header.step_time_ms as u64), start, first),
+            next: first,
+            end,
+            sleep: Box::pin(sleep_until(start.into())),
+            decode: None,
+            fseq_file: self,
+        }
+    }
+}
+
+// ----------------------------------------------------------------------
+// Frames Iterator
+// ----------------------------------------------------------------------
+/// Iterator over decoded frames, returned by `FseqFile::frames` and `FseqFile::frames_range`.
+///
+/// Each frame is read through `FseqFile::read_frame_into`, so decoded blocks are shared with
+/// every other reader through the block cache and the next block is prefetched as usual.
+pub struct Frames<'a> {
+    fseq_file: &'a FseqFile,
+    next: u32,
+    end: u32,
+}
+
+impl Iterator for Frames<'_> {
+    type Item = FseqResult<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next >= self.end {
+            return None;
+        }
+
+        let frame_num = self.next;
+        self.next += 1;
+        Some(self.fseq_file.get_frame(frame_num))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = (self.end - self.next) as usize;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for Frames<'_> {}
+
+// ----------------------------------------------------------------------
+// FrameStream
+// ----------------------------------------------------------------------
+/// Async stream of `(timestamp, frame)` pairs, returned by `FseqFile::frame_stream`.
+pub struct FrameStream {
+    fseq_file: Arc<FseqFile>,
+    /// Deadlines of the streamed frames; the first frame is due when the stream is created.
+    clock: FrameClock,
+    next: u32,
+    end: u32,
+    sleep: Pin<Box<Sleep>>,
+    /// The frame being decoded on the blocking pool, if any.
+    decode: Option<(u32, JoinHandle<FseqResult<Vec<u8>>>)>,
+}
+
+impl Stream for FrameStream {
+    type Item = FseqResult<(Duration, Vec<u8>)>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some((frame_num, decode)) = &mut this.decode {
+                let joined = ready!(Pin::new(decode).poll(cx));
+                let timestamp = this.clock.step() * *frame_num;
+                this.decode = None;
+
+                let frame_data = match joined {
+                    Ok(frame_data) => frame_data,
+                    Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+                    Err(e) => Err(FseqError::Io(io::Error::other(e))),
+                };
+                return Poll::Ready(Some(frame_data.map(|frame_data| (timestamp, frame_data))));
+            }
+
+            if this.next >= this.end {
+                return Poll::Ready(None);
+            }
+
+            ready!(this.sleep.as_mut().poll(cx));
+
+            // After a stall, continue with the frame due now rather than every frame missed.
+            let frame_num = this.clock.due_frame(Instant::now()).max(this.next);
+            if frame_num >= this.end {
+                this.next = this.end;
+                return Poll::Ready(None);
+            }
+            this.next = frame_num + 1;
+            this.sleep.as_mut().reset(this.clock.deadline(this.next).into());
+
+            let fseq_file = this.fseq_file.clone();
+            this.decode = Some((frame_num, tokio::task::spawn_blocking(move || fseq_file.get_frame(frame_num))));
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        // Frames may be skipped after a stall, so only the one being decoded is certain.
+        let decoding = usize::from(self.decode.is_some());
+        (decoding, Some(decoding + (self.end - self.next) as usize))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fseq::CompressionType;
+    use crate::test_data::{self, V2Fixture};
+    use tokio_stream::StreamExt;
+
+    #[test]
+    fn iterates_every_fixture() {
+        for (name, buffer) in test_data::v2_fixtures() {
+            let file = FseqFile::parse(buffer).unwrap();
+            assert_eq!(file.frames().len(), file.get_frame_count() as usize);
+
+            for (frame, frame_data) in file.frames().enumerate() {
+                assert_eq!(frame_data.unwrap(), test_data::frame_data(frame as u32, file.get_channel_count()), "{}: frame {} differs", name, frame);
+            }
+        }
+    }
+
+    #[test]
+    fn iterates_a_clamped_range() {
+        let file = FseqFile::parse(V2Fixture::new(CompressionType::Zlib, 300, 20, 64).build()).unwrap();
+
+        let frames: Vec<Vec<u8>> = file.frames_range(120..140).map(Result::unwrap).collect();
+        assert_eq!(frames.len(), 20);
+        assert_eq!(frames[0], test_data::frame_data(120, 20));
+        assert_eq!(frames[19], test_data::frame_data(139, 20));
+
+        assert_eq!(file.frames_range(290..1000).count(), 10);
+        assert_eq!(file.frames_range(500..1000).count(), 0);
+    }
+
+    #[tokio::test]
+    async fn streams_frames_at_step_time() {
+        let file = Arc::new(FseqFile::parse(V2Fixture::new(CompressionType::Zstd, 100, 12, 16).build()).unwrap());
+        let step = Duration::from_millis(test_data::STEP_TIME_MS as u64);
+
+        let started = Instant::now();
+        let items: Vec<(Duration, Vec<u8>)> = file.frame_stream(40..44).map(Result::unwrap).collect().await;
+
+        assert_eq!(items.iter().map(|(timestamp, _)| *timestamp).collect::<Vec<_>>(), vec![step * 40, step * 41, step * 42, step * 43]);
+        assert_eq!(items[3].1, test_data::frame_data(43, 12));
+        assert!(started.elapsed() >= step * 3);
+    }
+
+    #[tokio::test]
+    async fn skips_to_the_due_frame_after_a_stall() {
+        let file = Arc::new(FseqFile::parse(V2Fixture::new(CompressionType::Zlib, 100, 12, 16).build()).unwrap());
+        let step = Duration::from_millis(test_data::STEP_TIME_MS as u64);
+
+        let mut stream = file.clone().frame_stream(10..100);
+        let (timestamp, _) = stream.next().await.unwrap().unwrap();
+        assert_eq!(timestamp, step * 10);
+
+        tokio::time::sleep(step * 4 + step / 2).await;
+        let (timestamp, frame_data) = stream.next().await.unwrap().unwrap();
+        assert!(timestamp >= step * 14, "frame at {:?} was emitted instead of skipped", timestamp);
+        assert_eq!(frame_data, test_data::frame_data((timestamp.as_millis() / step.as_millis()) as u32, 12));
+
+        // A stream that is consumed after its last frame was due ends without a backlog.
+        let mut stream = file.frame_stream(0..3);
+        tokio::time::sleep(step * 4).await;
+        assert!(stream.next().await.is_none());
+    }
+}
@@ -0,0 +1,258 @@
+// src/fseq_native.rs
+use crate::fseq_error::{FseqError, FseqResult};
+use crate::fseq::{
+    ChannelRange, CompressionType, FseqHeader, FseqParser, RawVariableHeader,
+    BLOCK_INDEX_ENTRY_SIZE, CHANNEL_RANGE_ENTRY_SIZE, FIXED_HEADER_SIZE, VARIABLE_HEADER_PREFIX_SIZE,
+};
+
+// ----------------------------------------------------------------------
+// NativeParser Struct
+// ----------------------------------------------------------------------
+/// Pure-Rust FSEQ v2 parser. Mirrors tinyfseq's reads without needing the C library at build time.
+pub struct NativeParser;
+
+fn read_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+fn read_u24(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], 0])
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    ((read_u32(data, offset + 4) as u64) << 32) | read_u32(data, offset) as u64
+}
+
+impl FseqParser for NativeParser {
+    fn read_header(buffer: &[u8]) -> FseqResult<FseqHeader> {
+        if buffer.len() < FIXED_HEADER_SIZE {
+            return Err(FseqError::HeaderTooSmall { size: buffer.len(), required: FIXED_HEADER_SIZE });
+        }
+
+        if &buffer[0..4] != b"PSEQ" {
+            return Err(FseqError::InvalidMagic([buffer[0], buffer[1], buffer[2], buffer[3]]));
+        }
+
+        let compression_type = match CompressionType::from_raw(buffer[20] & 0x0F) {
+            Some(compression_type) => compression_type,
+            None => return Err(FseqError::UnsupportedCompression(buffer[20] & 0x0F)),
+        };
+
+        // The upper nibble of byte 20 extends the block count in byte 21 to 12 bits.
+        let compression_block_count = (((buffer[20] & 0xF0) as u16) << 4) | buffer[21] as u16;
+
+        Ok(FseqHeader {
+            channel_data_offset: read_u16(buffer, 4),
+            minor_version: buffer[6],
+            major_version: buffer[7],
+            variable_data_offset: read_u16(buffer, 8),
+            channel_count: read_u32(buffer, 10),
+            frame_count: read_u32(buffer, 14),
+            step_time_ms: buffer[18],
+            compression_type,
+            compression_block_count,
+            channel_range_count: buffer[22],
+            sequence_uid: read_u64(buffer, 24),
+        })
+    }
+
+    fn read_compression_block(entry: &[u8]) -> FseqResult<(u32, u32)> {
+        if entry.len() < BLOCK_INDEX_ENTRY_SIZE {
+            return Err(FseqError::InvalidHeader(format!("compression block entry is {} bytes (expected {})", entry.len(), BLOCK_INDEX_ENTRY_SIZE)));
+        }
+
+        Ok((read_u32(entry, 0), read_u32(entry, 4)))
+    }
+
+    fn read_channel_range(entry: &[u8]) -> FseqResult<ChannelRange> {
+        if entry.len() < CHANNEL_RANGE_ENTRY_SIZE {
+            return Err(FseqError::InvalidHeader(format!("sparse channel range entry is {} bytes (expected {})", entry.len(), CHANNEL_RANGE_ENTRY_SIZE)));
+        }
+
+        Ok(ChannelRange {
+            start_channel: read_u24(entry, 0),
+            channel_count: read_u24(entry, 3),
+        })
+    }
+
+    fn read_variable_header(data: &[u8]) -> FseqResult<RawVariableHeader> {
+        if data.len() < VARIABLE_HEADER_PREFIX_SIZE {
+            return Err(FseqError::InvalidHeader(format!("variable header needs {} bytes, only {} available", VARIABLE_HEADER_PREFIX_SIZE, data.len())));
+        }
+
+        let size = read_u16(data, 0);
+        if (size as usize) < VARIABLE_HEADER_PREFIX_SIZE {
+            return Err(FseqError::InvalidHeader(format!("variable header size {} is smaller than its {}-byte prefix", size, VARIABLE_HEADER_PREFIX_SIZE)));
+        }
+        if size as usize > data.len() {
+            return Err(FseqError::InvalidHeader(format!("variable header size {} exceeds the {} bytes available", size, data.len())));
+        }
+
+        Ok(RawVariableHeader {
+            size,
+            code: [data[2], data[3]],
+            data: data[VARIABLE_HEADER_PREFIX_SIZE..size as usize].to_vec(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fseq::{FseqFile, VariableHeader};
+    use crate::test_data::{self, V2Fixture};
+
+    fn assert_all_frames_match(file: &FseqFile, name: &str) {
+        for frame in 0..file.get_frame_count() {
+            let data = file.get_frame(frame).unwrap_or_else(|e| panic!("{}: frame {} failed: {}", name, frame, e));
+            assert_eq!(data, test_data::frame_data(frame, file.get_channel_count()), "{}: frame {} differs", name, frame);
+        }
+    }
+
+    #[test]
+    fn reads_fixed_header() {
+        let buffer = V2Fixture::new(CompressionType::Zstd, 600, 50, 64).build();
+        let header = NativeParser::read_header(&buffer).unwrap();
+
+        assert_eq!(header.major_version, 2);
+        assert_eq!(header.frame_count, 600);
+        assert_eq!(header.channel_count, 50);
+        assert_eq!(header.step_time_ms, test_data::STEP_TIME_MS);
+        assert_eq!(header.compression_type, CompressionType::Zstd);
+        assert_eq!(header.compression_block_count, 10);
+        assert_eq!(header.sequence_uid, 0x1234_5678);
+    }
+
+    #[test]
+    fn reads_extended_block_count() {
+        let mut buffer = V2Fixture::new(CompressionType::None, 10, 3, 0).build();
+        buffer[20] = 0x31;
+        buffer[21] = 0x02;
+
+        let header = NativeParser::read_header(&buffer).unwrap();
+        assert_eq!(header.compression_type, CompressionType::Zstd);
+        assert_eq!(header.compression_block_count, 0x302);
+    }
+
+    #[test]
+    fn rejects_invalid_headers() {
+        let buffer = V2Fixture::new(CompressionType::None, 10, 3, 0).build();
+
+        let mut bad_magic = buffer.clone();
+        bad_magic[0] = b'X';
+        assert!(matches!(NativeParser::read_header(&bad_magic), Err(FseqError::InvalidMagic(magic)) if &magic == b"XSEQ"));
+
+        let mut bad_compression = buffer.clone();
+        bad_compression[20] = 0x07;
+        assert!(matches!(NativeParser::read_header(&bad_compression), Err(FseqError::UnsupportedCompression(7))));
+
+        assert!(matches!(NativeParser::read_header(&buffer[..31]), Err(FseqError::HeaderTooSmall { size: 31, .. })));
+    }
+
+    #[test]
+    fn decodes_every_fixture_frame() {
+        for (name, buffer) in test_data::v2_fixtures() {
+            let file = FseqFile::parse_with::<NativeParser>(buffer).unwrap();
+            assert_all_frames_match(&file, name);
+        }
+
+        for magic in [b"PSEQ", b"FSEQ"] {
+            let file = FseqFile::parse_with::<NativeParser>(test_data::build_v1(120, 33, magic)).unwrap();
+            assert_eq!(file.get_version(), (1, 0));
+            assert_eq!(file.get_step_time(), test_data::STEP_TIME_MS as i32);
+            assert_all_frames_match(&file, "v1");
+        }
+    }
+
+    #[test]
+    fn reads_frames_into_reused_buffer() {
+        for (name, buffer) in test_data::v2_fixtures() {
+            let file = FseqFile::parse_with::<NativeParser>(buffer).unwrap();
+            let mut frame_data = vec![0u8; file.frame_size()];
+
+            for frame in (0..file.get_frame_count()).rev() {
+                file.read_frame_into(frame, &mut frame_data).unwrap();
+                assert_eq!(frame_data, test_data::frame_data(frame, file.get_channel_count()), "{}: frame {} differs", name, frame);
+            }
+
+            assert!(file.read_frame_into(0, &mut frame_data[1..]).is_err());
+            assert!(file.read_frame_into(file.get_frame_count(), &mut frame_data).is_err());
+        }
+    }
+
+    #[test]
+    fn reports_typed_frame_errors() {
+        let buffer = V2Fixture::new(CompressionType::Zstd, 300, 50, 100).build();
+        let file = FseqFile::parse_with::<NativeParser>(buffer.clone()).unwrap();
+        let last_block = *file.get_compression_blocks().last().unwrap();
+
+        let error = file.get_frame(300).unwrap_err();
+        assert!(matches!(error, FseqError::FrameOutOfRange { frame: 300, frame_count: 300 }));
+        assert!(!error.is_recoverable());
+
+        let truncated = FseqFile::parse_with::<NativeParser>(buffer[..buffer.len() - 1].to_vec()).unwrap();
+        assert!(truncated.get_frame(0).is_ok());
+        let error = truncated.get_frame(250).unwrap_err();
+        assert!(matches!(error, FseqError::TruncatedBlock { block: 2, .. }));
+        assert!(error.is_recoverable());
+
+        let mut corrupt = buffer;
+        let block_offset = last_block.offset as usize;
+        corrupt[block_offset..block_offset + 8].fill(0xAA);
+        let corrupt = FseqFile::parse_with::<NativeParser>(corrupt).unwrap();
+        assert!(matches!(corrupt.get_frame(250), Err(FseqError::DecompressFailed { compression: CompressionType::Zstd, .. })));
+    }
+
+    #[test]
+    fn builds_block_index_from_table() {
+        let mut fixture = V2Fixture::new(CompressionType::Zstd, 600, 50, 256);
+        fixture.small_first_block = true;
+        fixture.padding_blocks = 2;
+        let file = FseqFile::parse_with::<NativeParser>(fixture.build()).unwrap();
+
+        let spans: Vec<(u32, u32)> = file.get_compression_blocks().iter()
+            .map(|block| (block.start_frame, block.frame_count))
+            .collect();
+        assert_eq!(spans, vec![(0, 10), (10, 256), (266, 256), (522, 78)]);
+
+        let blocks = file.get_compression_blocks();
+        for pair in blocks.windows(2) {
+            assert_eq!(pair[0].offset + pair[0].size as u64, pair[1].offset);
+        }
+    }
+
+    #[test]
+    fn reads_sparse_ranges_and_segments() {
+        let mut fixture = V2Fixture::new(CompressionType::Zlib, 300, 40, 100);
+        fixture.sparse_ranges = vec![(100, 15), (1000, 25)];
+        let file = FseqFile::parse_with::<NativeParser>(fixture.build()).unwrap();
+
+        assert!(file.is_sparse());
+        assert_eq!(file.get_channel_ranges(), &[
+            ChannelRange { start_channel: 100, channel_count: 15 },
+            ChannelRange { start_channel: 1000, channel_count: 25 },
+        ]);
+
+        let frame = file.get_frame(7).unwrap();
+        let segments: Vec<(u32, usize)> = file.frame_segments(&frame)
+            .map(|segment| (segment.start_channel, segment.data.len()))
+            .collect();
+        assert_eq!(segments, vec![(100, 15), (1000, 25)]);
+    }
+
+    #[test]
+    fn reads_variable_headers() {
+        let file = FseqFile::parse_with::<NativeParser>(V2Fixture::new(CompressionType::None, 10, 3, 0).build()).unwrap();
+
+        assert_eq!(file.variable_headers(), &[
+            VariableHeader::MediaFilename(test_data::MEDIA_FILENAME.to_string()),
+            VariableHeader::SequenceProducer(test_data::SEQUENCE_PRODUCER.to_string()),
+            VariableHeader::Unknown { code: *b"zz", data: vec![1, 2, 3] },
+        ]);
+        assert_eq!(file.get_media_filename(), Some(test_data::MEDIA_FILENAME));
+    }
+}
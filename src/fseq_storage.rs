@@ -0,0 +1,225 @@
+// src/fseq_storage.rs
+use memmap2::Mmap;
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::{Mutex, PoisonError};
+use crate::fseq_error::{FseqError, FseqResult};
+
+/// Files up to this size are read into memory by `LoadMode::Auto`; larger ones are memory-mapped,
+/// or streamed when they do not fit the address space.
+pub const AUTO_MEMORY_LIMIT: u64 = 256 * 1024 * 1024;
+
+// ----------------------------------------------------------------------
+// LoadMode Enum
+// ----------------------------------------------------------------------
+/// How an FSEQ file's bytes are made available to the decoder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum LoadMode {
+    /// Read small files into memory and memory-map large ones, streaming those that cannot be mapped.
+    Auto,
+    /// Read the whole file into memory.
+    Memory,
+    /// Memory-map the file and let the OS page frames in on demand.
+    Mmap,
+    /// Seek and read each frame or compressed block from the file on demand.
+    Stream,
+}
+
+// ----------------------------------------------------------------------
+// FseqStorage Enum
+// ----------------------------------------------------------------------
+/// Backing store for the raw bytes of an FSEQ file.
+///
+/// Offsets and lengths are `u64` so files larger than the address space (multi-GB shows on
+/// 32-bit players) can still be streamed.
+pub enum FseqStorage {
+    Memory(Vec<u8>),
+    Mapped(Mmap),
+    Streamed { file: Mutex<File>, len: u64 },
+}
+
+impl FseqStorage {
+    /// Opens `path` with the requested load mode.
+    pub fn open(path: impl AsRef<Path>, mode: LoadMode) -> FseqResult<Self> {
+        let path = path.as_ref();
+        let open_failed = |source| FseqError::Open { path: path.to_path_buf(), source };
+        let file = File::open(path).map_err(open_failed)?;
+        let len = file.metadata().map_err(open_failed)?.len();
+
+        match auto_load_mode(mode, len, usize::MAX as u64) {
+            LoadMode::Memory => {
+                let capacity = usize::try_from(len).map_err(|_| open_failed(io::Error::new(
+                    io::ErrorKind::OutOfMemory, format!("{} bytes do not fit in memory; use --load-mode stream", len))))?;
+                let mut buffer = Vec::with_capacity(capacity);
+                (&file).read_to_end(&mut buffer).map_err(open_failed)?;
+                Ok(FseqStorage::Memory(buffer))
+            }
+            LoadMode::Mmap => {
+                // Safety: the mapping is read-only; the show file must not be truncated while it plays.
+                match unsafe { Mmap::map(&file) } {
+                    Ok(mmap) => Ok(FseqStorage::Mapped(mmap)),
+                    Err(e) if mode == LoadMode::Auto => {
+                        log::debug!("Memory-mapping {} failed ({}), streaming it instead.", path.display(), e);
+                        Ok(FseqStorage::Streamed { file: Mutex::new(file), len })
+                    }
+                    Err(e) => Err(open_failed(e)),
+                }
+            }
+            LoadMode::Stream | LoadMode::Auto => Ok(FseqStorage::Streamed { file: Mutex::new(file), len }),
+        }
+    }
+
+    pub fn len(&self) -> u64 {
+        match self {
+            FseqStorage::Memory(buffer) => buffer.len() as u64,
+            FseqStorage::Mapped(mmap) => mmap.len() as u64,
+            FseqStorage::Streamed { len, .. } => *len,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the whole file when it is held in (or mapped into) memory.
+    pub fn as_slice(&self) -> Option<&[u8]> {
+        match self {
+            FseqStorage::Memory(buffer) => Some(buffer),
+            FseqStorage::Mapped(mmap) => Some(mmap),
+            FseqStorage::Streamed { .. } => None,
+        }
+    }
+
+    /// Reads `len` bytes at `offset`, borrowing from memory when possible.
+    pub fn read_at(&self, offset: u64, len: usize) -> FseqResult<Cow<'_, [u8]>> {
+        let end = self.check_range(offset, len)?;
+
+        match self.as_slice() {
+            // In-memory files fit the address space, so their offsets fit usize.
+            Some(bytes) => Ok(Cow::Borrowed(&bytes[offset as usize..end as usize])),
+            None => {
+                let mut data = vec![0u8; len];
+                self.read_into(offset, &mut data)?;
+                Ok(Cow::Owned(data))
+            }
+        }
+    }
+
+    /// Fills `out` with the bytes at `offset` without allocating.
+    pub fn read_into(&self, offset: u64, out: &mut [u8]) -> FseqResult<()> {
+        let end = self.check_range(offset, out.len())?;
+
+        match self {
+            FseqStorage::Memory(_) | FseqStorage::Mapped(_) => {
+                let bytes = self.as_slice().expect("in-memory storage");
+                out.copy_from_slice(&bytes[offset as usize..end as usize]);
+            }
+            FseqStorage::Streamed { file, .. } => {
+                let mut file = file.lock().unwrap_or_else(PoisonError::into_inner);
+                file.seek(SeekFrom::Start(offset))?;
+                file.read_exact(out)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the end of the `offset..offset + len` range, failing if it lies outside the file.
+    fn check_range(&self, offset: u64, len: usize) -> FseqResult<u64> {
+        match offset.checked_add(len as u64) {
+            Some(end) if end <= self.len() => Ok(end),
+            end => Err(FseqError::Truncated { start: offset, end: end.unwrap_or(u64::MAX), file_size: self.len() }),
+        }
+    }
+}
+
+/// Resolves `LoadMode::Auto` for a file of `len` bytes: memory for small files, a mapping for
+/// large ones and streaming for files longer than `max_mapped_len`, the address space size.
+fn auto_load_mode(mode: LoadMode, len: u64, max_mapped_len: u64) -> LoadMode {
+    match mode {
+        LoadMode::Auto if len <= AUTO_MEMORY_LIMIT => LoadMode::Memory,
+        LoadMode::Auto if len <= max_mapped_len => LoadMode::Mmap,
+        LoadMode::Auto => LoadMode::Stream,
+        other => other,
+    }
+}
+
+impl From<Vec<u8>> for FseqStorage {
+    fn from(buffer: Vec<u8>) -> Self {
+        FseqStorage::Memory(buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fseq::{CompressionType, FseqFile};
+    use crate::test_data::{self, V2Fixture};
+    use std::io::Write;
+
+    /// Every load mode must decode the same frames as an in-memory parse.
+    #[test]
+    fn load_modes_decode_identical_frames() {
+        let directory = std::env::temp_dir().join(format!("fseq-storage-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+
+        for (name, buffer) in test_data::v2_fixtures() {
+            let path = directory.join(format!("{}.fseq", name));
+            std::fs::write(&path, &buffer).unwrap();
+
+            for mode in [LoadMode::Auto, LoadMode::Memory, LoadMode::Mmap, LoadMode::Stream] {
+                let file = FseqFile::open(&path, mode).unwrap();
+                for frame in 0..file.get_frame_count() {
+                    assert_eq!(file.get_frame(frame).unwrap(), test_data::frame_data(frame, file.get_channel_count()),
+                               "{} ({:?}): frame {} differs", name, mode, frame);
+                }
+            }
+        }
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn rejects_reads_past_the_end() {
+        let storage = FseqStorage::from(vec![0u8; 16]);
+        assert!(storage.read_at(8, 8).is_ok());
+        assert!(storage.read_at(8, 9).is_err());
+        assert!(storage.read_at(u64::MAX, 2).is_err());
+    }
+
+    #[test]
+    fn auto_streams_files_larger_than_the_address_space() {
+        let four_gib = 4 * 1024 * 1024 * 1024;
+        assert_eq!(auto_load_mode(LoadMode::Auto, 1024, u32::MAX as u64), LoadMode::Memory);
+        assert_eq!(auto_load_mode(LoadMode::Auto, AUTO_MEMORY_LIMIT + 1, u32::MAX as u64), LoadMode::Mmap);
+        assert_eq!(auto_load_mode(LoadMode::Auto, four_gib, u32::MAX as u64), LoadMode::Stream);
+        assert_eq!(auto_load_mode(LoadMode::Auto, four_gib, u64::MAX), LoadMode::Mmap);
+        assert_eq!(auto_load_mode(LoadMode::Memory, four_gib, u32::MAX as u64), LoadMode::Memory);
+    }
+
+    /// A sparse multi-GB file whose last frames lie past 4 GiB, read through the stream path.
+    #[test]
+    fn streams_frames_past_four_gib() {
+        const CHANNELS: u32 = 1024 * 1024;
+        const FRAMES: u32 = 4200;
+
+        let mut buffer = V2Fixture::new(CompressionType::None, 1, CHANNELS, 0).build();
+        buffer[14..18].copy_from_slice(&FRAMES.to_le_bytes());
+        let channel_data_offset = u16::from_le_bytes([buffer[4], buffer[5]]) as u64;
+
+        let path = std::env::temp_dir().join(format!("fseq-storage-large-{}.fseq", std::process::id()));
+        let file = File::create(&path).unwrap();
+        (&file).write_all(&buffer).unwrap();
+        file.set_len(channel_data_offset + FRAMES as u64 * CHANNELS as u64).unwrap();
+        drop(file);
+
+        let fseq_file = FseqFile::open(&path, LoadMode::Stream).unwrap();
+        assert!(fseq_file.file_size() > u32::MAX as u64);
+        assert_eq!(fseq_file.get_frame(0).unwrap(), test_data::frame_data(0, CHANNELS));
+        assert!(fseq_file.get_frame(FRAMES - 1).unwrap().iter().all(|value| *value == 0));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
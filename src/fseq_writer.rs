@@ -0,0 +1,639 @@
+// src/fseq_writer.rs
+use anyhow::{Result, bail, Context};
+use std::fs::{self, File};
+use std::io::{BufWriter, Cursor, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use flate2::write::ZlibEncoder;
+use crate::fseq::{
+    ChannelRange, CompressionType, VariableHeader,
+    BLOCK_INDEX_ENTRY_SIZE, CHANNEL_RANGE_ENTRY_SIZE, FIXED_HEADER_SIZE, V1_FIXED_HEADER_SIZE, VARIABLE_HEADER_PREFIX_SIZE,
+};
+
+/// Frames per compression block unless configured otherwise.
+pub const DEFAULT_FRAMES_PER_BLOCK: u32 = 256;
+
+/// Largest block count the 12-bit extended block count field can hold.
+pub const MAX_COMPRESSION_BLOCKS: usize = 0x0FFF;
+
+/// zstd level used for frame data; low levels keep decompression cheap on small players.
+const ZSTD_LEVEL: i32 = 3;
+
+// ----------------------------------------------------------------------
+// FseqVersion Enum
+// ----------------------------------------------------------------------
+/// On-disk layout written by `FseqWriter`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum FseqVersion {
+    /// FSEQ v1: uncompressed and dense, read by older players and controllers.
+    V1,
+    /// FSEQ v2: optional compression and sparse channel ranges.
+    V2,
+}
+
+// ----------------------------------------------------------------------
+// FseqWriter Struct
+// ----------------------------------------------------------------------
+/// Builds an FSEQ v2 (or v1) file from frames appended in order.
+///
+/// Frames appended here are held in memory and encoded by `to_bytes` or `save`; `stream_to`
+/// writes them to their destination as they arrive instead.
+pub struct FseqWriter {
+    version: FseqVersion,
+    channel_count: u32,
+    step_time_ms: u8,
+    compression_type: CompressionType,
+    /// Frames per compression block; 0 writes a single compressed stream without a block table.
+    frames_per_block: u32,
+    /// Size of the first block, which xLights keeps small so playback can start quickly.
+    first_block_frames: Option<u32>,
+    channel_ranges: Vec<ChannelRange>,
+    variable_headers: Vec<VariableHeader>,
+    sequence_uid: u64,
+    frame_count: u32,
+    frame_data: Vec<u8>,
+}
+
+impl FseqWriter {
+    /// Creates an uncompressed writer for frames of `channel_count` channels played every `step_time_ms`.
+    pub fn new(channel_count: u32, step_time_ms: u8) -> Self {
+        FseqWriter {
+            version: FseqVersion::V2,
+            channel_count,
+            step_time_ms,
+            compression_type: CompressionType::None,
+            frames_per_block: DEFAULT_FRAMES_PER_BLOCK,
+            first_block_frames: None,
+            channel_ranges: Vec::new(),
+            variable_headers: Vec::new(),
+            sequence_uid: 0,
+            frame_count: 0,
+            frame_data: Vec::new(),
+        }
+    }
+
+    /// Sets the file layout. v1 files cannot be compressed or sparse.
+    pub fn with_version(mut self, version: FseqVersion) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Sets the frame data compression.
+    pub fn with_compression(mut self, compression_type: CompressionType) -> Self {
+        self.compression_type = compression_type;
+        self
+    }
+
+    /// Sets the number of frames per compression block; 0 compresses all frames as a single stream.
+    pub fn with_frames_per_block(mut self, frames_per_block: u32) -> Self {
+        self.frames_per_block = frames_per_block;
+        self
+    }
+
+    /// Writes a shorter first block of `frames` frames, as xLights does for fast playback start.
+    pub fn with_first_block_frames(mut self, frames: u32) -> Self {
+        self.first_block_frames = Some(frames);
+        self
+    }
+
+    /// Stores frames as sparse channel ranges; their channel counts must add up to the channel count.
+    pub fn with_channel_ranges(mut self, channel_ranges: Vec<ChannelRange>) -> Self {
+        self.channel_ranges = channel_ranges;
+        self
+    }
+
+    /// Adds a variable header, written in the order added.
+    pub fn with_variable_header(mut self, variable_header: VariableHeader) -> Self {
+        self.variable_headers.push(variable_header);
+        self
+    }
+
+    /// Sets the sequence UID stored in the fixed header.
+    pub fn with_sequence_uid(mut self, sequence_uid: u64) -> Self {
+        self.sequence_uid = sequence_uid;
+        self
+    }
+
+    /// Appends one frame of exactly `channel_count` bytes.
+    pub fn append_frame(&mut self, frame_data: &[u8]) -> Result<()> {
+        if frame_data.len() != self.channel_count as usize {
+            bail!("Frame {} is {} bytes, but the sequence has {} channels.", self.frame_count, frame_data.len(), self.channel_count);
+        }
+        if self.frame_count == u32::MAX {
+            bail!("FSEQ files cannot hold more than {} frames.", u32::MAX);
+        }
+
+        self.frame_data.extend_from_slice(frame_data);
+        self.frame_count += 1;
+        Ok(())
+    }
+
+    pub fn frame_count(&self) -> u32 {
+        self.frame_count
+    }
+
+    /// Encodes the complete file.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        let mut stream = self.layout().stream_to(Cursor::new(Vec::new()), self.frame_count)?;
+        let frame_size = self.channel_count as usize;
+        for frame in 0..self.frame_count as usize {
+            stream.append_frame(&self.frame_data[frame * frame_size..(frame + 1) * frame_size])?;
+        }
+        Ok(stream.finish()?.into_inner())
+    }
+
+    /// Starts writing a file of exactly `frame_count` frames to `out`, which must be positioned
+    /// at the start of the file. Frames are then appended to the returned stream writer.
+    pub fn stream_to<W: Write + Seek>(self, mut out: W, frame_count: u32) -> Result<FseqStreamWriter<W>> {
+        self.validate()?;
+
+        let block_count = self.block_count(frame_count);
+        if block_count > MAX_COMPRESSION_BLOCKS {
+            bail!("{} compression blocks exceed the FSEQ limit of {}; use more frames per block.", block_count, MAX_COMPRESSION_BLOCKS);
+        }
+
+        let header = match self.version {
+            FseqVersion::V1 => self.encode_v1_header(frame_count)?,
+            FseqVersion::V2 => self.encode_v2_header(frame_count, block_count)?,
+        };
+        out.write_all(&header).context("Failed to write FSEQ header")?;
+
+        let sink = match self.compression_type {
+            CompressionType::Zstd if block_count == 0 => {
+                FrameSink::Zstd(zstd::Encoder::new(out, ZSTD_LEVEL).context("Failed to start ZSTD compression.")?)
+            }
+            CompressionType::Zlib if block_count == 0 => {
+                FrameSink::Zlib(ZlibEncoder::new(out, flate2::Compression::default()))
+            }
+            _ => FrameSink::Raw(out),
+        };
+
+        Ok(FseqStreamWriter {
+            layout: self,
+            sink,
+            frame_count,
+            frames_written: 0,
+            block_count,
+            block_table: Vec::with_capacity(block_count),
+            block_start: 0,
+            pending: Vec::new(),
+        })
+    }
+
+    /// Copies the layout settings without any appended frames.
+    fn layout(&self) -> FseqWriter {
+        FseqWriter {
+            version: self.version,
+            channel_count: self.channel_count,
+            step_time_ms: self.step_time_ms,
+            compression_type: self.compression_type,
+            frames_per_block: self.frames_per_block,
+            first_block_frames: self.first_block_frames,
+            channel_ranges: self.channel_ranges.clone(),
+            variable_headers: self.variable_headers.clone(),
+            sequence_uid: self.sequence_uid,
+            frame_count: 0,
+            frame_data: Vec::new(),
+        }
+    }
+
+    /// Number of compression blocks a file of `frame_count` frames is split into.
+    /// Uncompressed and single-stream files (including empty ones) have no block table.
+    fn block_count(&self, frame_count: u32) -> usize {
+        if self.compression_type == CompressionType::None || self.frames_per_block == 0 || frame_count == 0 {
+            return 0;
+        }
+
+        let first_block_frames = self.first_block_frames.unwrap_or(self.frames_per_block).min(frame_count);
+        1 + (frame_count - first_block_frames).div_ceil(self.frames_per_block) as usize
+    }
+
+    /// Number of frames in the compression block starting at `first_frame`.
+    fn block_span(&self, first_frame: u32, frame_count: u32) -> u32 {
+        let span = match self.first_block_frames {
+            Some(first_block_frames) if first_frame == 0 => first_block_frames,
+            _ => self.frames_per_block,
+        };
+        span.min(frame_count - first_frame)
+    }
+
+    /// Encodes the v2 header region: the fixed header, a zeroed block table of `block_count`
+    /// entries, the sparse ranges and the variable headers, padded up to the channel data.
+    fn encode_v2_header(&self, frame_count: u32, block_count: usize) -> Result<Vec<u8>> {
+        let variable_headers = self.encode_variable_headers()?;
+
+        let variable_data_offset = FIXED_HEADER_SIZE
+            + block_count * BLOCK_INDEX_ENTRY_SIZE
+            + self.channel_ranges.len() * CHANNEL_RANGE_ENTRY_SIZE;
+        // Channel data starts on a 4-byte boundary, as xLights and FPP write it.
+        let channel_data_offset = (variable_data_offset + variable_headers.len() + 3) & !3;
+        if channel_data_offset > u16::MAX as usize {
+            bail!("FSEQ header region ({} bytes) does not fit the 16-bit channel data offset.", channel_data_offset);
+        }
+
+        let mut out = Vec::with_capacity(channel_data_offset);
+
+        out.extend_from_slice(b"PSEQ");
+        out.extend_from_slice(&(channel_data_offset as u16).to_le_bytes());
+        out.extend_from_slice(&[0, 2]);
+        out.extend_from_slice(&(variable_data_offset as u16).to_le_bytes());
+        out.extend_from_slice(&self.channel_count.to_le_bytes());
+        out.extend_from_slice(&frame_count.to_le_bytes());
+        out.extend_from_slice(&[self.step_time_ms, 0]);
+        // The upper nibble of byte 20 extends the block count in byte 21 to 12 bits.
+        out.push(self.compression_type.to_raw() | ((((block_count >> 8) & 0x0F) as u8) << 4));
+        out.push((block_count & 0xFF) as u8);
+        out.extend_from_slice(&[self.channel_ranges.len() as u8, 0]);
+        out.extend_from_slice(&self.sequence_uid.to_le_bytes());
+
+        // Block sizes are only known once each block is written; `finish` fills the table in.
+        out.resize(FIXED_HEADER_SIZE + block_count * BLOCK_INDEX_ENTRY_SIZE, 0);
+
+        for channel_range in &self.channel_ranges {
+            out.extend_from_slice(&channel_range.start_channel.to_le_bytes()[..3]);
+            out.extend_from_slice(&channel_range.channel_count.to_le_bytes()[..3]);
+        }
+
+        out.extend_from_slice(&variable_headers);
+        out.resize(channel_data_offset, 0);
+        Ok(out)
+    }
+
+    /// Encodes an FSEQ v1 header region: a 28-byte fixed header and the variable headers.
+    fn encode_v1_header(&self, frame_count: u32) -> Result<Vec<u8>> {
+        let variable_headers = self.encode_variable_headers()?;
+        let channel_data_offset = (V1_FIXED_HEADER_SIZE + variable_headers.len() + 3) & !3;
+        if channel_data_offset > u16::MAX as usize {
+            bail!("FSEQ header region ({} bytes) does not fit the 16-bit channel data offset.", channel_data_offset);
+        }
+
+        let mut out = Vec::with_capacity(channel_data_offset);
+
+        out.extend_from_slice(b"PSEQ");
+        out.extend_from_slice(&(channel_data_offset as u16).to_le_bytes());
+        out.extend_from_slice(&[0, 1]);
+        out.extend_from_slice(&(V1_FIXED_HEADER_SIZE as u16).to_le_bytes());
+        out.extend_from_slice(&self.channel_count.to_le_bytes());
+        out.extend_from_slice(&frame_count.to_le_bytes());
+        out.extend_from_slice(&(self.step_time_ms as u16).to_le_bytes());
+        // Universe count and size are unused for DDP output; gamma 1, RGB color encoding.
+        out.extend_from_slice(&[0, 0, 0, 0, 1, 1, 0, 0]);
+
+        out.extend_from_slice(&variable_headers);
+        out.resize(channel_data_offset, 0);
+        Ok(out)
+    }
+
+    /// Encodes the variable headers as (u16 size, 2-byte code, value) entries.
+    fn encode_variable_headers(&self) -> Result<Vec<u8>> {
+        let mut variable_headers = Vec::new();
+        for variable_header in &self.variable_headers {
+            let value = variable_header.to_raw_value();
+            let size = VARIABLE_HEADER_PREFIX_SIZE + value.len();
+            if size > u16::MAX as usize {
+                bail!("Variable header {} is too large ({} bytes).", variable_header, size);
+            }
+            variable_headers.extend_from_slice(&(size as u16).to_le_bytes());
+            variable_headers.extend_from_slice(&variable_header.code());
+            variable_headers.extend_from_slice(&value);
+        }
+
+        Ok(variable_headers)
+    }
+
+    /// Encodes the file and writes it to `writer`.
+    pub fn write_to(&self, mut writer: impl Write) -> Result<()> {
+        writer.write_all(&self.to_bytes()?).context("Failed to write FSEQ data")?;
+        writer.flush().context("Failed to flush FSEQ data")
+    }
+
+    /// Encodes the file and saves it at `path`, replacing any existing file once it is complete.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let (pending_file, file) = PendingFile::create(path.as_ref())?;
+        self.write_to(BufWriter::new(file))?;
+        pending_file.commit()
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.step_time_ms == 0 {
+            bail!("Step time must be at least 1ms.");
+        }
+
+        if self.channel_ranges.len() > u8::MAX as usize {
+            bail!("{} sparse channel ranges exceed the FSEQ limit of {}.", self.channel_ranges.len(), u8::MAX);
+        }
+
+        const U24_MAX: u32 = 0xFF_FFFF;
+        for channel_range in &self.channel_ranges {
+            if channel_range.start_channel > U24_MAX || channel_range.channel_count > U24_MAX {
+                bail!("Sparse channel range {:?} does not fit the 24-bit range fields.", channel_range);
+            }
+        }
+
+        if !self.channel_ranges.is_empty() {
+            let range_channels: u64 = self.channel_ranges.iter().map(|range| range.channel_count as u64).sum();
+            if range_channels != self.channel_count as u64 {
+                bail!("Sparse channel ranges cover {} channels, but frames have {}.", range_channels, self.channel_count);
+            }
+        }
+
+        if self.version == FseqVersion::V1 {
+            if self.compression_type != CompressionType::None {
+                bail!("FSEQ v1 files cannot be compressed.");
+            }
+            if !self.channel_ranges.is_empty() {
+                bail!("FSEQ v1 files cannot hold sparse channel ranges.");
+            }
+        }
+
+        if self.first_block_frames == Some(0) {
+            bail!("The first compression block must hold at least one frame.");
+        }
+
+        Ok(())
+    }
+}
+
+// ----------------------------------------------------------------------
+// FseqStreamWriter Struct
+// ----------------------------------------------------------------------
+/// Writes an FSEQ file to its destination as frames are appended, returned by `FseqWriter::stream_to`.
+///
+/// Each compression block is compressed and written as soon as it fills, so at most one block
+/// of frames is held in memory. The block table is patched in by `finish`.
+pub struct FseqStreamWriter<W: Write + Seek> {
+    layout: FseqWriter,
+    sink: FrameSink<W>,
+    /// Frame count declared in the header.
+    frame_count: u32,
+    frames_written: u32,
+    /// Number of compression blocks reserved in the block table; 0 if there is none.
+    block_count: usize,
+    /// (first frame, compressed size) of each block written so far.
+    block_table: Vec<(u32, u32)>,
+    /// First frame of the block being filled.
+    block_start: u32,
+    /// Frames of the block being filled.
+    pending: Vec<u8>,
+}
+
+/// Where appended frames go: straight to the output, or through a single-stream encoder.
+enum FrameSink<W: Write> {
+    /// Uncompressed frames and compressed blocks.
+    Raw(W),
+    Zstd(zstd::Encoder<'static, W>),
+    Zlib(ZlibEncoder<W>),
+}
+
+impl<W: Write + Seek> FseqStreamWriter<W> {
+    /// Appends one frame of exactly `channel_count` bytes.
+    pub fn append_frame(&mut self, frame_data: &[u8]) -> Result<()> {
+        if frame_data.len() != self.layout.channel_count as usize {
+            bail!("Frame {} is {} bytes, but the sequence has {} channels.", self.frames_written, frame_data.len(), self.layout.channel_count);
+        }
+        if self.frames_written == self.frame_count {
+            bail!("The file was started with {} frames; no more can be appended.", self.frame_count);
+        }
+
+        let written = match &mut self.sink {
+            FrameSink::Raw(_) if self.block_count > 0 => {
+                self.pending.extend_from_slice(frame_data);
+                Ok(())
+            }
+            FrameSink::Raw(out) => out.write_all(frame_data),
+            FrameSink::Zstd(encoder) => encoder.write_all(frame_data),
+            FrameSink::Zlib(encoder) => encoder.write_all(frame_data),
+        };
+        written.context("Failed to write FSEQ frame data")?;
+        self.frames_written += 1;
+
+        if self.block_count > 0 && self.frames_written == self.block_start + self.layout.block_span(self.block_start, self.frame_count) {
+            self.write_block()?;
+        }
+        Ok(())
+    }
+
+    pub fn frames_written(&self) -> u32 {
+        self.frames_written
+    }
+
+    /// Compresses and writes the pending block.
+    fn write_block(&mut self) -> Result<()> {
+        let FrameSink::Raw(out) = &mut self.sink else {
+            unreachable!("blocks are only written without a single-stream encoder");
+        };
+
+        let compressed = compress(self.layout.compression_type, &self.pending)?;
+        let Ok(size) = u32::try_from(compressed.len()) else {
+            bail!("Compression block at frame {} is {} bytes, more than FSEQ can index.", self.block_start, compressed.len());
+        };
+        out.write_all(&compressed).context("Failed to write FSEQ frame data")?;
+
+        self.block_table.push((self.block_start, size));
+        self.block_start = self.frames_written;
+        self.pending.clear();
+        Ok(())
+    }
+
+    /// Completes the file: ends the single compressed stream or patches the block table, and
+    /// returns the output. Fails unless exactly the declared number of frames was appended.
+    pub fn finish(self) -> Result<W> {
+        if self.frames_written != self.frame_count {
+            bail!("Only {} of the {} frames the file was started with were appended.", self.frames_written, self.frame_count);
+        }
+
+        let mut out = match self.sink {
+            FrameSink::Raw(out) => out,
+            FrameSink::Zstd(encoder) => encoder.finish().context("Failed to compress ZSTD frame data.")?,
+            FrameSink::Zlib(encoder) => encoder.finish().context("Failed to compress ZLIB frame data.")?,
+        };
+
+        if !self.block_table.is_empty() {
+            let patch_failed = "Failed to write the FSEQ compression block table";
+            let data_end = out.stream_position().context(patch_failed)?;
+            out.seek(SeekFrom::Start(FIXED_HEADER_SIZE as u64)).context(patch_failed)?;
+            for (first_frame, size) in &self.block_table {
+                out.write_all(&first_frame.to_le_bytes()).context(patch_failed)?;
+                out.write_all(&size.to_le_bytes()).context(patch_failed)?;
+            }
+            out.seek(SeekFrom::Start(data_end)).context(patch_failed)?;
+        }
+
+        out.flush().context("Failed to flush FSEQ data")?;
+        Ok(out)
+    }
+}
+
+// ----------------------------------------------------------------------
+// PendingFile Struct
+// ----------------------------------------------------------------------
+/// An output file written under a temporary name in its destination's directory and renamed
+/// over the destination once complete. A failed write never leaves a truncated file behind,
+/// and the destination may be the very file being read, as in an in-place `convert`.
+pub struct PendingFile {
+    path: PathBuf,
+    temp_path: PathBuf,
+    committed: bool,
+}
+
+impl PendingFile {
+    /// Creates the temporary file for `path`.
+    pub fn create(path: &Path) -> Result<(Self, File)> {
+        let Some(file_name) = path.file_name() else {
+            bail!("Invalid FSEQ output path: {}", path.display());
+        };
+        let temp_path = path.with_file_name(format!(".{}.{}.tmp", file_name.to_string_lossy(), std::process::id()));
+        let file = File::create(&temp_path).context(format!("Failed to create FSEQ file at: {}", temp_path.display()))?;
+        Ok((PendingFile { path: path.to_path_buf(), temp_path, committed: false }, file))
+    }
+
+    /// Moves the completed file over the destination.
+    pub fn commit(mut self) -> Result<()> {
+        fs::rename(&self.temp_path, &self.path)
+            .context(format!("Failed to move the new FSEQ file into place at: {}", self.path.display()))?;
+        self.committed = true;
+        Ok(())
+    }
+}
+
+impl Drop for PendingFile {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_file(&self.temp_path);
+        }
+    }
+}
+
+/// Compresses frame data with the encoder matching `compression_type`.
+fn compress(compression_type: CompressionType, data: &[u8]) -> Result<Vec<u8>> {
+    match compression_type {
+        CompressionType::Zstd => zstd::encode_all(data, ZSTD_LEVEL).context("Failed to compress ZSTD frame data."),
+        CompressionType::Zlib => {
+            let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(data).context("Failed to compress ZLIB frame data.")?;
+            encoder.finish().context("Failed to compress ZLIB frame data.")
+        }
+        CompressionType::None => Ok(data.to_vec()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fseq::FseqFile;
+    use crate::test_data;
+
+    fn writer_with_frames(frames: u32, channels: u32) -> FseqWriter {
+        let mut writer = FseqWriter::new(channels, test_data::STEP_TIME_MS);
+        for frame in 0..frames {
+            writer.append_frame(&test_data::frame_data(frame, channels)).unwrap();
+        }
+        writer
+    }
+
+    #[test]
+    fn round_trips_through_fseq_file() {
+        for compression_type in [CompressionType::None, CompressionType::Zstd, CompressionType::Zlib] {
+            let writer = writer_with_frames(500, 48)
+                .with_compression(compression_type)
+                .with_frames_per_block(64)
+                .with_first_block_frames(10)
+                .with_sequence_uid(42)
+                .with_variable_header(VariableHeader::MediaFilename(test_data::MEDIA_FILENAME.to_string()));
+            let file = FseqFile::parse(writer.to_bytes().unwrap()).unwrap();
+
+            assert_eq!(file.header.compression_type, compression_type);
+            assert_eq!(file.header.compression_block_count as usize, file.get_compression_blocks().len());
+            assert_eq!(file.header.sequence_uid, 42);
+            assert_eq!(file.get_step_time(), test_data::STEP_TIME_MS as i32);
+            assert_eq!(file.get_media_filename(), Some(test_data::MEDIA_FILENAME));
+            if compression_type != CompressionType::None {
+                assert_eq!(file.get_compression_blocks()[..2].iter().map(|block| block.frame_count).collect::<Vec<_>>(), vec![10, 64]);
+            }
+
+            for frame in 0..file.get_frame_count() {
+                assert_eq!(file.get_frame(frame).unwrap(), test_data::frame_data(frame, 48), "{}: frame {} differs", compression_type, frame);
+            }
+        }
+    }
+
+    #[test]
+    fn round_trips_uncompressed_files() {
+        let writer = writer_with_frames(300, 20)
+            .with_frames_per_block(64)
+            .with_channel_ranges(vec![ChannelRange { start_channel: 0, channel_count: 8 }, ChannelRange { start_channel: 100, channel_count: 12 }]);
+        let buffer = writer.to_bytes().unwrap();
+
+        // Uncompressed files have no block table, whatever the block size, and hold the raw frames.
+        assert_eq!(buffer[20], CompressionType::None.to_raw());
+        assert_eq!(buffer[21], 0);
+        let channel_data_offset = u16::from_le_bytes([buffer[4], buffer[5]]) as usize;
+        assert_eq!(buffer.len(), channel_data_offset + 300 * 20);
+        assert_eq!(&buffer[channel_data_offset..channel_data_offset + 20], test_data::frame_data(0, 20).as_slice());
+
+        let file = FseqFile::parse(buffer).unwrap();
+        assert!(file.get_compression_blocks().is_empty());
+        assert_eq!(file.get_channel_ranges().len(), 2);
+        for frame in 0..file.get_frame_count() {
+            assert_eq!(file.get_frame(frame).unwrap(), test_data::frame_data(frame, 20), "frame {} differs", frame);
+        }
+    }
+
+    #[test]
+    fn writes_v1_files() {
+        let writer = writer_with_frames(120, 33)
+            .with_version(FseqVersion::V1)
+            .with_variable_header(VariableHeader::MediaFilename(test_data::MEDIA_FILENAME.to_string()));
+        let file = FseqFile::parse(writer.to_bytes().unwrap()).unwrap();
+
+        assert_eq!(file.get_version(), (1, 0));
+        assert_eq!(file.get_media_filename(), Some(test_data::MEDIA_FILENAME));
+        for frame in 0..file.get_frame_count() {
+            assert_eq!(file.get_frame(frame).unwrap(), test_data::frame_data(frame, 33));
+        }
+
+        assert!(writer_with_frames(1, 3).with_version(FseqVersion::V1).with_compression(CompressionType::Zstd).to_bytes().is_err());
+    }
+
+    #[test]
+    fn streams_blocks_as_they_fill() {
+        let mut stream = FseqWriter::new(16, test_data::STEP_TIME_MS)
+            .with_compression(CompressionType::Zstd)
+            .with_frames_per_block(10)
+            .stream_to(Cursor::new(Vec::new()), 25)
+            .unwrap();
+
+        for frame in 0..25 {
+            stream.append_frame(&test_data::frame_data(frame, 16)).unwrap();
+            // Frames wait only until their block, or the short last block, is full.
+            let pending_frames = if frame == 24 { 0 } else { (frame as usize + 1) % 10 };
+            assert_eq!(stream.pending.len(), pending_frames * 16);
+        }
+        assert!(stream.append_frame(&test_data::frame_data(25, 16)).is_err());
+
+        let file = FseqFile::parse(stream.finish().unwrap().into_inner()).unwrap();
+        assert_eq!(file.get_compression_blocks().iter().map(|block| block.frame_count).collect::<Vec<_>>(), vec![10, 10, 5]);
+        assert_eq!(file.get_frame(24).unwrap(), test_data::frame_data(24, 16));
+
+        let mut short = FseqWriter::new(16, test_data::STEP_TIME_MS).stream_to(Cursor::new(Vec::new()), 3).unwrap();
+        short.append_frame(&test_data::frame_data(0, 16)).unwrap();
+        assert!(short.finish().is_err());
+
+        // Empty compressed files are written as a single stream, which has no block table.
+        let empty = FseqWriter::new(16, test_data::STEP_TIME_MS).with_compression(CompressionType::Zlib).to_bytes().unwrap();
+        assert_eq!(FseqFile::parse(empty).unwrap().get_frame_count(), 0);
+    }
+
+    #[test]
+    fn rejects_invalid_layouts() {
+        let mut writer = FseqWriter::new(10, 25);
+        assert!(writer.append_frame(&[0; 9]).is_err());
+
+        let sparse = writer_with_frames(1, 10).with_channel_ranges(vec![ChannelRange { start_channel: 0, channel_count: 9 }]);
+        assert!(sparse.to_bytes().is_err());
+
+        let too_many_blocks = writer_with_frames(4096, 1).with_compression(CompressionType::Zstd).with_frames_per_block(1);
+        assert!(too_many_blocks.to_bytes().is_err());
+
+        assert!(FseqWriter::new(10, 0).to_bytes().is_err());
+    }
+}
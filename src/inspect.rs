@@ -0,0 +1,274 @@
+// src/inspect.rs
+use anyhow::Result;
+use serde::Serialize;
+use std::fmt;
+use crate::fseq::{ChannelRange, CompressionBlock, CompressionType, FseqFile};
+use crate::fseq_error::{Severity, ValidationReport};
+use crate::fseq_storage::LoadMode;
+
+/// Exit code of `validate` when the file parses but has errors.
+pub const EXIT_INVALID: i32 = 1;
+
+/// Exit code of `validate` when the file cannot be opened or its header cannot be parsed.
+pub const EXIT_UNREADABLE: i32 = 2;
+
+// ----------------------------------------------------------------------
+// SequenceInfo Struct
+// ----------------------------------------------------------------------
+/// Summary of an FSEQ file printed by the `info` subcommand.
+#[derive(Debug, Clone, Serialize)]
+pub struct SequenceInfo {
+    pub file: String,
+    pub file_size: u64,
+    pub version: String,
+    pub channel_count: u32,
+    pub frame_count: u32,
+    pub step_time_ms: u8,
+    pub frames_per_second: f64,
+    pub duration_secs: f64,
+    pub compression: CompressionType,
+    pub sequence_uid: u64,
+    pub channel_data_offset: u16,
+    pub variable_data_offset: u16,
+    /// Channel bytes sent per second during playback.
+    pub channel_data_rate: f64,
+    /// File bytes read per second during playback, i.e. after compression.
+    pub stored_data_rate: f64,
+    pub variable_headers: Vec<VariableHeaderInfo>,
+    pub compression_blocks: Vec<CompressionBlock>,
+    pub channel_ranges: Vec<ChannelRange>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct VariableHeaderInfo {
+    pub code: String,
+    pub value: String,
+}
+
+impl SequenceInfo {
+    pub fn new(file: &str, fseq_file: &FseqFile) -> Self {
+        let header = &fseq_file.header;
+        let duration_secs = header.frame_count as f64 * header.step_time_ms as f64 / 1000.0;
+        let stored_data_size = fseq_file.file_size().saturating_sub(header.channel_data_offset as u64);
+        let per_second = |bytes: u64| if duration_secs > 0.0 { bytes as f64 / duration_secs } else { 0.0 };
+
+        SequenceInfo {
+            file: file.to_string(),
+            file_size: fseq_file.file_size(),
+            version: format!("{}.{}", header.major_version, header.minor_version),
+            channel_count: header.channel_count,
+            frame_count: header.frame_count,
+            step_time_ms: header.step_time_ms,
+            // A zero step time is reported by `validate`; it has no meaningful rate.
+            frames_per_second: if header.step_time_ms > 0 { 1000.0 / header.step_time_ms as f64 } else { 0.0 },
+            duration_secs,
+            compression: header.compression_type,
+            sequence_uid: header.sequence_uid,
+            channel_data_offset: header.channel_data_offset,
+            variable_data_offset: header.variable_data_offset,
+            channel_data_rate: per_second(header.frame_count as u64 * fseq_file.frame_size() as u64),
+            stored_data_rate: per_second(stored_data_size),
+            variable_headers: fseq_file.variable_headers().iter()
+                .map(|variable_header| VariableHeaderInfo {
+                    code: String::from_utf8_lossy(&variable_header.code()).into_owned(),
+                    value: variable_header.display_value(),
+                })
+                .collect(),
+            compression_blocks: fseq_file.get_compression_blocks().to_vec(),
+            channel_ranges: fseq_file.get_channel_ranges().to_vec(),
+        }
+    }
+}
+
+impl fmt::Display for SequenceInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "File: {} ({} bytes)", self.file, self.file_size)?;
+        writeln!(f, "Version: {}", self.version)?;
+        writeln!(f, "Channels: {}", self.channel_count)?;
+        writeln!(f, "Frames: {} @ {}ms ({:.2} fps)", self.frame_count, self.step_time_ms, self.frames_per_second)?;
+        writeln!(f, "Duration: {}", format_duration(self.duration_secs))?;
+        writeln!(f, "Compression: {}", self.compression)?;
+        writeln!(f, "Sequence UID: {}", self.sequence_uid)?;
+        writeln!(f, "Channel Data Offset: {}", self.channel_data_offset)?;
+        writeln!(f, "Variable Data Offset: {}", self.variable_data_offset)?;
+        writeln!(f, "Data Rate: {} (stored: {})", format_rate(self.channel_data_rate), format_rate(self.stored_data_rate))?;
+
+        if !self.variable_headers.is_empty() {
+            writeln!(f, "--- Variable Headers ---")?;
+            for variable_header in &self.variable_headers {
+                writeln!(f, "{}: {}", variable_header.code, variable_header.value)?;
+            }
+        }
+
+        if !self.compression_blocks.is_empty() {
+            writeln!(f, "--- Compression Blocks ---")?;
+            for (index, block) in self.compression_blocks.iter().enumerate() {
+                writeln!(f, "Block {}: frames {}-{}, offset {}, size {}",
+                         index, block.start_frame, block.start_frame + block.frame_count - 1, block.offset, block.size)?;
+            }
+        }
+
+        if !self.channel_ranges.is_empty() {
+            writeln!(f, "--- Sparse Channel Ranges ---")?;
+            for (index, range) in self.channel_ranges.iter().enumerate() {
+                writeln!(f, "Range {}: start channel {}, {} channels", index, range.start_channel, range.channel_count)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn format_duration(duration_secs: f64) -> String {
+    let total_millis = (duration_secs * 1000.0).round() as u64;
+    format!("{}:{:02}.{:03}", total_millis / 60_000, (total_millis / 1000) % 60, total_millis % 1000)
+}
+
+fn format_rate(bytes_per_second: f64) -> String {
+    if bytes_per_second >= 1024.0 * 1024.0 {
+        format!("{:.2} MiB/s", bytes_per_second / (1024.0 * 1024.0))
+    } else {
+        format!("{:.1} KiB/s", bytes_per_second / 1024.0)
+    }
+}
+
+// ----------------------------------------------------------------------
+// Subcommand Entry Points
+// ----------------------------------------------------------------------
+/// Prints the `info` summary of `path` as text or JSON.
+pub fn print_info(path: &str, json: bool) -> Result<()> {
+    let fseq_file = FseqFile::open(path, LoadMode::Auto)?;
+    let info = SequenceInfo::new(path, &fseq_file);
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&info)?);
+    } else {
+        print!("{}", info);
+    }
+
+    Ok(())
+}
+
+/// Validates `path`, prints the report as text or JSON and returns the process exit code.
+pub fn run_validation(path: &str, json: bool) -> i32 {
+    #[derive(Serialize)]
+    struct Output<'a> {
+        file: &'a str,
+        valid: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+        #[serde(flatten, skip_serializing_if = "Option::is_none")]
+        report: Option<ValidationReport>,
+    }
+
+    let (exit_code, output) = match FseqFile::open(path, LoadMode::Auto) {
+        Ok(fseq_file) => {
+            let report = fseq_file.validate();
+            let exit_code = if report.is_valid() { 0 } else { EXIT_INVALID };
+            (exit_code, Output { file: path, valid: exit_code == 0, error: None, report: Some(report) })
+        }
+        Err(e) => (EXIT_UNREADABLE, Output { file: path, valid: false, error: Some(format!("{:#}", e)), report: None }),
+    };
+
+    if json {
+        match serde_json::to_string_pretty(&output) {
+            Ok(rendered) => println!("{}", rendered),
+            Err(e) => eprintln!("Failed to encode the validation report: {}", e),
+        }
+        return exit_code;
+    }
+
+    if let Some(error) = &output.error {
+        println!("{}: unreadable: {}", path, error);
+        return exit_code;
+    }
+
+    if let Some(report) = &output.report {
+        for issue in &report.issues {
+            let severity = match issue.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+            };
+            println!("{}: {}: {}", path, severity, issue.message);
+        }
+        println!("{}: {} ({} frames decoded, {} issues)",
+                 path, if output.valid { "OK" } else { "INVALID" }, report.frames_decoded, report.issues.len());
+    }
+
+    exit_code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fseq_error::IssueKind;
+    use crate::test_data::{self, V2Fixture};
+
+    #[test]
+    fn reports_sequence_info() {
+        let mut fixture = V2Fixture::new(CompressionType::Zstd, 400, 30, 100);
+        fixture.sparse_ranges = vec![(0, 10), (100, 20)];
+        let fseq_file = FseqFile::parse(fixture.build()).unwrap();
+        let info = SequenceInfo::new("show.fseq", &fseq_file);
+
+        assert_eq!(info.version, "2.0");
+        assert_eq!(info.duration_secs, 10.0);
+        assert_eq!(info.frames_per_second, 40.0);
+        assert_eq!(info.channel_data_rate, 1200.0);
+        assert_eq!(info.compression_blocks.len(), 4);
+        assert_eq!(info.channel_ranges.len(), 2);
+        assert_eq!(info.variable_headers[0].code, "mf");
+        assert_eq!(info.variable_headers[0].value, test_data::MEDIA_FILENAME);
+
+        let json: serde_json::Value = serde_json::to_value(&info).unwrap();
+        assert_eq!(json["compression"], "zstd");
+        assert_eq!(json["compression_blocks"][1]["start_frame"], 100);
+        assert!(info.to_string().contains("Duration: 0:10.000"));
+    }
+
+    #[test]
+    fn accepts_every_fixture() {
+        for (name, buffer) in test_data::v2_fixtures() {
+            let report = FseqFile::parse(buffer).unwrap().validate();
+            assert!(report.issues.is_empty(), "{}: {:?}", name, report.issues);
+        }
+    }
+
+    #[test]
+    fn reports_truncated_and_corrupt_data() {
+        let buffer = V2Fixture::new(CompressionType::Zstd, 300, 50, 100).build();
+        let truncated = FseqFile::parse(buffer[..buffer.len() - 10].to_vec()).unwrap().validate();
+        assert!(!truncated.is_valid());
+        assert_eq!(truncated.frames_decoded, 200);
+        assert!(truncated.issues.iter().any(|issue| issue.kind == IssueKind::Truncated));
+        assert!(truncated.issues.iter().any(|issue| issue.message.starts_with("Frames 200-299")));
+
+        let mut corrupt = buffer.clone();
+        let first_block = FseqFile::parse(buffer.clone()).unwrap().get_compression_blocks()[0];
+        let block_offset = first_block.offset as usize;
+        corrupt[block_offset + 4..block_offset + 12].fill(0xAA);
+        let corrupt = FseqFile::parse(corrupt).unwrap().validate();
+        assert!(corrupt.issues.iter().any(|issue| issue.kind == IssueKind::DecodeFailed));
+
+        let mut padded = V2Fixture::new(CompressionType::None, 10, 5, 0).build();
+        padded.extend_from_slice(&[0; 3]);
+        let padded = FseqFile::parse(padded).unwrap().validate();
+        assert!(padded.is_valid());
+        assert_eq!(padded.issues[0].kind, IssueKind::TrailingData);
+    }
+
+    #[test]
+    fn reports_zero_step_time() {
+        let mut buffer = V2Fixture::new(CompressionType::None, 10, 5, 0).build();
+        buffer[18] = 0;
+        let fseq_file = FseqFile::parse(buffer).unwrap();
+
+        let info = SequenceInfo::new("show.fseq", &fseq_file);
+        assert_eq!(info.frames_per_second, 0.0);
+        assert_eq!(serde_json::to_value(&info).unwrap()["frames_per_second"], 0.0);
+
+        let report = fseq_file.validate();
+        assert!(!report.is_valid());
+        assert_eq!(report.issues[0].kind, IssueKind::BadStepTime);
+    }
+}
@@ -1,98 +1,224 @@
 // src/lib.rs
 use anyhow::{Context, Result};
-use std::fs::read;
-use std::net::{Ipv4Addr, SocketAddr};
-use ddp_rs::{connection::DDPConnection, protocol::PixelConfig};
-use crate::fseq_ffi::FseqFile;
-use crate::playback::{PlaybackContext, run_playback_loop}; 
-use crate::monitor::run_monitor_thread;
-use tokio::sync::watch;
+use crate::fseq_storage::LoadMode;
+use crate::output::{ControllerMap, Output};
+use crate::playback::{PlaybackContext, PlaybackPosition, ResumePolicy, SequenceLoader, SequenceTiming, run_playback_loop}; 
+use crate::playlist::Playlist;
+use crate::scheduler::{OutsideBehavior, Schedule, run_scheduler};
+use crate::monitor::{ClaimPolicy, ControllerHealth, MonitorConfig, run_monitor_thread};
+use tokio::sync::watch::{self, Receiver};
 use tokio::task;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+pub mod fseq;
+pub mod fseq_error;
+pub mod fseq_frames;
+#[cfg(feature = "native")]
+pub mod fseq_native;
+#[cfg(feature = "tinyfseq")]
 pub mod fseq_ffi;
+pub mod fseq_storage;
+pub mod fseq_writer;
+pub mod block_cache;
+pub mod output;
 pub mod playback;
+pub mod playlist;
+pub mod scheduler;
+pub mod solar;
+pub mod frame_clock;
 pub mod monitor;
+pub mod probe;
 pub mod cli;
+pub mod convert;
+pub mod inspect;
+
+#[cfg(test)]
+mod test_data;
 
 #[derive(Clone, Debug)]
 pub struct PlaybackConfig {
-    pub fseq_path: String,
-    pub wled_ip_address: String, 
+    pub playlist: Playlist,
+    /// Where each part of the sequence's channel space is sent.
+    pub controllers: ControllerMap,
+    /// How often controllers are checked and when they count as down or up again.
+    pub monitor: MonitorConfig,
     pub loop_enabled: bool,
-    pub ddp_port: u16,
+    pub block_cache_mb: usize,
+    pub prefetch_enabled: bool,
+    pub load_mode: LoadMode,
+    pub resume_policy: ResumePolicy,
+    /// When to play; `None` plays whenever the controller is up.
+    pub schedule: Option<Schedule>,
+}
+
+// ----------------------------------------------------------------------
+// Show Struct
+// ----------------------------------------------------------------------
+/// How often black frames are resent while the schedule blanks the lights, so the controller
+/// stays in realtime mode instead of falling back to its own presets.
+pub(crate) const BLANK_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A playlist ready to play: one per schedule window, or just the default playlist.
+struct Show {
+    name: String,
+    playlist: Arc<Playlist>,
+    timings: Arc<Vec<SequenceTiming>>,
+}
+
+impl Show {
+    fn prepare(name: String, playlist: &Playlist) -> Result<Self> {
+        playlist.validate().with_context(|| format!("No playlist for {}", name))?;
+        // Every entry is checked before the show starts, so a bad path fails now rather than mid-show.
+        let timings = playlist
+            .entries
+            .iter()
+            .map(|entry| SequenceTiming::probe(&entry.path))
+            .collect::<Result<Vec<_>>>()?;
+        log::info!("Playlist for {}: {} sequences{}.", name, timings.len(), if playlist.shuffle { ", shuffled" } else { "" });
+
+        Ok(Show { name, playlist: Arc::new(playlist.clone()), timings: Arc::new(timings) })
+    }
 }
 
 // ----------------------------------------------------------------------
 // Main Play Sequence Orchestration
 // ----------------------------------------------------------------------
+/// Streams the show while the monitor reports the controller up and, with a schedule, while
+/// a schedule window is open.
 pub async fn play_sequence(config: PlaybackConfig) -> Result<()> {
     
     log::info!("Starting FSEQ Player...");
     log::info!("Configuration: {:?}", config);
-    
-    let buffer = read(&config.fseq_path)
-        .context(format!("Failed to read FSEQ file at: {}", config.fseq_path))?;
-    let fseq_file = FseqFile::parse(buffer)?;
-    let fseq_arc = Arc::new(fseq_file);
-    let (tx_stream_state, rx_stream_state) = watch::channel(false);
-    
-    run_monitor_thread(config.clone(), tx_stream_state);
 
-    let mut rx_player_state = rx_stream_state.clone();
-    let player_config = config;
+    let shows = match &config.schedule {
+        Some(schedule) => schedule
+            .windows
+            .iter()
+            .map(|window| Show::prepare(format!("window {:?}", window.name), window.playlist.as_ref().unwrap_or(&config.playlist)))
+            .collect::<Result<Vec<_>>>()?,
+        None => vec![Show::prepare("the show".to_string(), &config.playlist)?],
+    };
+    let outside = config.schedule.as_ref().map_or(OutsideBehavior::Idle, |schedule| schedule.outside);
+    let blank_frame = vec![0u8; shows.iter().flat_map(|show| show.timings.iter()).map(|timing| timing.channel_extent as usize).max().unwrap_or(0)];
+    let loader = SequenceLoader {
+        load_mode: config.load_mode,
+        block_cache_bytes: config.block_cache_mb * 1024 * 1024,
+        prefetch_enabled: config.prefetch_enabled,
+    };
+
+    let claim_policy = config.monitor.claim_policy;
+    let (tx_health, mut rx_health) = watch::channel(ControllerHealth::default());
+    // Whether the player is streaming, so the monitor can poll faster while it does.
+    let (tx_streaming, rx_streaming) = watch::channel(false);
+    run_monitor_thread(config.clone(), tx_health, rx_streaming);
+
+    // Index into `shows` of the open window, or `None` outside all windows.
+    let (tx_active_show, mut rx_active_show) = watch::channel(if config.schedule.is_some() { None } else { Some(0) });
+    // Without a schedule the single show is always active; the sender is held so the channel stays open.
+    let _tx_active_show = match &config.schedule {
+        Some(schedule) => {
+            run_scheduler(schedule.clone(), tx_active_show);
+            None
+        }
+        None => Some(tx_active_show),
+    };
+
+    // Outlives each playback loop so a START after a STOP can resume per the resume policy.
+    let mut position = Arc::new(PlaybackPosition::new());
+    let mut position_show = None;
     
     loop {
-        // Wait for the START signal (state change to true)
-        if !*rx_player_state.borrow() {
-            log::info!("Player: Waiting for START signal from Monitor...");
-            rx_player_state.changed().await.context("Monitor thread stopped unexpectedly.")?;
-        }
-        
-        // Ensure state is true (START) and proceed
-        if *rx_player_state.borrow() {
-            log::info!("Player: Received START signal. Initializing DDP connection...");
-
-            // Set up DDP connection
-            let local_socket = std::net::UdpSocket::bind(
-                SocketAddr::new(std::net::IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), 0)
-            ).context("Failed to bind local UDP socket")?;
-        
-            let conn = DDPConnection::try_new(
-                &format!("{}:{}", player_config.wled_ip_address, player_config.ddp_port),
-                PixelConfig::default(),
-                ddp_rs::protocol::ID::Default,
-                local_socket 
-            )?;
-
-            let context = PlaybackContext {
-                fseq_file: fseq_arc.clone(),
-                loop_enabled: player_config.loop_enabled,
-            };
-
-            // Run the blocking playback loop on a separate *tokio* blocking thread
-            let rx_stop = rx_player_state.clone();
-            let mut join_handle = task::spawn_blocking(move || { 
-                run_playback_loop(conn, context, rx_stop)
-            });
-
-            // Wait for termination condition (Monitor STOP or sequence finished)
-            loop {
-                tokio::select! {
-                    res = &mut join_handle => { 
-                        match res {
-                            Ok(Ok(_)) => log::info!("Player: Playback loop finished naturally."),
-                            Ok(Err(e)) => log::error!("Player: Playback loop crashed: {}", e),
-                            Err(e) => log::error!("Player: Playback thread panicked: {}", e),
-                        }
-                        break;
+        // Playback runs while any controller is up; the output skips the ones that are down or claimed.
+        let device_up = rx_health.borrow_and_update().playable(claim_policy);
+        let active_show = *rx_active_show.borrow_and_update();
+        tx_streaming.send_replace(device_up && (active_show.is_some() || outside == OutsideBehavior::Blank));
+
+        match (device_up, active_show) {
+            (false, _) => {
+                log::info!("Player: Waiting for START signal from Monitor...");
+                wait_for_change(&mut rx_health, &mut rx_active_show).await?;
+            }
+            (true, None) if outside == OutsideBehavior::Blank => {
+                log::info!("Player: Outside the schedule. Blanking lights...");
+                let mut output = Output::connect(&config.controllers)?.with_health(rx_health.clone());
+                loop {
+                    output.send(0, &blank_frame);
+                    tokio::select! {
+                        _ = tokio::time::sleep(BLANK_REFRESH_INTERVAL) => {}
+                        res = wait_for_change(&mut rx_health, &mut rx_active_show) => { res?; break; }
                     }
-                    _ = rx_player_state.changed() => {
-                        if !*rx_player_state.borrow() {
-                            log::info!("Player: Monitor requested STOP. Waiting for playback thread to terminate...");
+                }
+            }
+            (true, None) => {
+                log::info!("Player: Outside the schedule. Idle until the next window opens.");
+                wait_for_change(&mut rx_health, &mut rx_active_show).await?;
+            }
+            (true, Some(show_index)) => {
+                // A newly opened window starts its playlist from the top; resuming only applies
+                // to controller dropouts within the same window.
+                if position_show != Some(show_index) {
+                    position = Arc::new(PlaybackPosition::new());
+                    position_show = Some(show_index);
+                }
+                let show = &shows[show_index];
+                log::info!("Player: Received START signal for {}. Initializing DDP connection...", show.name);
+                let output = Output::connect(&config.controllers)?.with_health(rx_health.clone());
+
+                let start = config.resume_policy.resume_point(
+                    &position,
+                    &show.playlist,
+                    &show.timings,
+                    config.loop_enabled,
+                    Instant::now(),
+                );
+
+                let context = PlaybackContext {
+                    playlist: show.playlist.clone(),
+                    timings: show.timings.clone(),
+                    loader,
+                    loop_enabled: config.loop_enabled,
+                    start,
+                    position: position.clone(),
+                };
+
+                // The playback thread runs until the show ends or this task clears its run flag.
+                let (tx_keep_playing, rx_keep_playing) = watch::channel(true);
+                let mut join_handle = task::spawn_blocking(move || { 
+                    run_playback_loop(output, context, rx_keep_playing)
+                });
+
+                // Wait for termination condition (Monitor STOP, schedule change or show finished)
+                loop {
+                    tokio::select! {
+                        res = &mut join_handle => { 
+                            match res {
+                                Ok(Ok(_)) => log::info!("Player: Playback loop finished naturally."),
+                                Ok(Err(e)) => log::error!("Player: Playback loop crashed: {}", e),
+                                Err(e) => log::error!("Player: Playback thread panicked: {}", e),
+                            }
+                            // Play again only once the controller or the schedule changes.
+                            wait_for_change(&mut rx_health, &mut rx_active_show).await?;
+                            break;
+                        }
+                        res = wait_for_change(&mut rx_health, &mut rx_active_show) => {
+                            res?;
+                            let still_playing = rx_health.borrow().playable(claim_policy) && *rx_active_show.borrow() == Some(show_index);
+                            if still_playing {
+                                continue;
+                            }
+
+                            if rx_health.borrow().any_claimed() && claim_policy == ClaimPolicy::Stop {
+                                log::info!("Player: A controller was claimed by another source. Waiting for playback thread to terminate...");
+                            } else if !rx_health.borrow().any_up() {
+                                log::info!("Player: Monitor reports every controller down or claimed. Waiting for playback thread to terminate...");
+                            } else {
+                                log::info!("Player: Schedule changed. Waiting for playback thread to terminate...");
+                            }
+                            let _ = tx_keep_playing.send(false);
                             
                             match (&mut join_handle).await { 
-                                Ok(Ok(_)) => log::info!("Player: Playback thread terminated gracefully by Monitor signal."),
+                                Ok(Ok(_)) => log::info!("Player: Playback thread terminated gracefully."),
                                 Ok(Err(e)) => log::error!("Player: Playback thread crashed during controlled shutdown: {}", e),
                                 Err(e) => log::error!("Player: Playback thread panicked during controlled shutdown: {}", e),
                             }
@@ -104,3 +230,11 @@ pub async fn play_sequence(config: PlaybackConfig) -> Result<()> {
         }
     }
 }
+
+/// Waits until the controller health or the active show changes.
+async fn wait_for_change(rx_health: &mut Receiver<ControllerHealth>, rx_active_show: &mut Receiver<Option<usize>>) -> Result<()> {
+    tokio::select! {
+        res = rx_health.changed() => res.context("Monitor thread stopped unexpectedly."),
+        res = rx_active_show.changed() => res.context("Scheduler stopped unexpectedly."),
+    }
+}
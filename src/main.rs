@@ -1,22 +1,71 @@
 // src/main.rs
-use anyhow::Result;
+use anyhow::{Context, Result};
 use clap::Parser;
-use wled_sequencer_lib::cli::Cli;
+use std::path::Path;
+use wled_sequencer_lib::cli::{Cli, Command};
+use wled_sequencer_lib::convert::{convert_sequence, ConvertConfig};
+use wled_sequencer_lib::inspect::{print_info, run_validation};
+use wled_sequencer_lib::monitor::MonitorConfig;
+use wled_sequencer_lib::output::ControllerMap;
+use wled_sequencer_lib::playlist::Playlist;
+use wled_sequencer_lib::scheduler::Schedule;
 use wled_sequencer_lib::{play_sequence, PlaybackConfig};
 
 #[tokio::main]
 async fn main() -> Result<()> {
     let log_env = env_logger::Env::default().filter_or("RUST_LOG", "wled_sequencer_lib=info");
     env_logger::init_from_env(log_env);
-    
+
     let cli = Cli::parse();
-    
-    let config = PlaybackConfig {
-        fseq_path: cli.file,
-        wled_ip_address: cli.host,
-        loop_enabled: cli.loop_enabled,
-        ddp_port: cli.port,
-    };
 
-    play_sequence(config).await
+    match cli.command {
+        Some(Command::Convert(args)) => {
+            let config = ConvertConfig {
+                input_path: args.input,
+                output_path: args.output,
+                compression: args.compression,
+                frames_per_block: args.frames_per_block,
+                version: args.version,
+                start_frame: args.start_frame,
+                end_frame: args.end_frame,
+            };
+
+            convert_sequence(&config)
+        }
+        Some(Command::Info(args)) => print_info(&args.file, args.json),
+        Some(Command::Validate(args)) => std::process::exit(run_validation(&args.file, args.json)),
+        None => {
+            let playlist = match &cli.play.playlist {
+                Some(path) => Playlist::load(Path::new(path))?,
+                None => Playlist::from_files(&cli.play.file).with_gap(cli.play.gap.unwrap_or_default()),
+            };
+            let shuffle = playlist.shuffle || cli.play.shuffle;
+            let controllers = match &cli.play.controllers {
+                Some(path) => ControllerMap::load(Path::new(path))?,
+                None => ControllerMap::single(&cli.play.host.context("--host is required")?, cli.play.port, cli.play.probe),
+            };
+
+            let config = PlaybackConfig {
+                playlist: playlist.with_shuffle(shuffle),
+                controllers,
+                monitor: MonitorConfig {
+                    idle_interval: cli.play.monitor_interval,
+                    streaming_interval: cli.play.streaming_monitor_interval,
+                    failure_threshold: cli.play.failure_threshold,
+                    success_threshold: cli.play.success_threshold,
+                    max_backoff: cli.play.max_backoff,
+                    claim_policy: cli.play.on_claimed,
+                    override_hold: cli.play.override_hold,
+                },
+                loop_enabled: cli.play.loop_enabled,
+                block_cache_mb: cli.play.block_cache_mb,
+                prefetch_enabled: !cli.play.no_prefetch,
+                load_mode: cli.play.load_mode,
+                resume_policy: cli.play.resume,
+                schedule: cli.play.schedule.as_deref().map(|path| Schedule::load(Path::new(path))).transpose()?,
+            };
+
+            play_sequence(config).await
+        }
+    }
 }
@@ -1,84 +1,524 @@
 // src/monitor.rs
-use std::process::Command;
+use std::collections::BTreeMap;
+use std::fmt;
 use std::thread;
-use std::time::Duration;
-use tokio::sync::watch::Sender;
+use std::time::{Duration, Instant};
+use tokio::sync::watch::{Receiver, Sender};
+use anyhow::Result;
 use crate::PlaybackConfig;
+use crate::output::Controller;
+use crate::probe::{HealthProbe, PROBE_TIMEOUT, RealtimeClaim, local_ip_towards, query_realtime_claim};
 
 // ----------------------------------------------------------------------
-// ICMP Monitoring Function
+// ControllerStatus Enum
 // ----------------------------------------------------------------------
-fn check_device_status(ip: &str) -> bool {
-    // Uses the system's 'ping' command, configured to send 1 packet and timeout quickly (1 second).
-    let output = if cfg!(target_os = "windows") {
-        Command::new("ping")
-            .args(&["-n", "1", "-w", "1000", ip])
-            .output()
-    } else {
-        Command::new("ping")
-            .args(&["-c", "1", "-W", "1", ip])
-            .output()
-    };
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ControllerStatus {
+    /// Not answering its probe, or not reached yet.
+    #[default]
+    Down,
+    Up,
+    /// Up, but WLED's realtime mode belongs to someone else.
+    Claimed(RealtimeClaim),
+}
 
-    match output {
-        Ok(output) => {
-            output.status.success()
+impl fmt::Display for ControllerStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ControllerStatus::Down => write!(f, "down"),
+            ControllerStatus::Up => write!(f, "up"),
+            ControllerStatus::Claimed(claim) => write!(f, "claimed by {}", claim),
         }
-        Err(e) => {
-            log::error!("Failed to execute system ping command (is 'ping' in PATH?): {:?}", e);
-            false
+    }
+}
+
+// ----------------------------------------------------------------------
+// ClaimPolicy Enum
+// ----------------------------------------------------------------------
+/// What the player does when another source claims a WLED controller's realtime mode.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ClaimPolicy {
+    /// Keep sending, overriding the other source. Controllers are not queried for claims.
+    Ignore,
+    /// Stop sending to the claimed controller until it is released; the others keep playing.
+    #[default]
+    Yield,
+    /// Stop the whole show until every controller is released.
+    Stop,
+}
+
+// ----------------------------------------------------------------------
+// ControllerHealth Struct
+// ----------------------------------------------------------------------
+/// The status of each controller, by name. Controllers the monitor has not reached yet are down.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ControllerHealth {
+    statuses: BTreeMap<String, ControllerStatus>,
+}
+
+impl ControllerHealth {
+    pub fn status(&self, name: &str) -> ControllerStatus {
+        self.statuses.get(name).cloned().unwrap_or_default()
+    }
+
+    /// Whether frames should be sent to the controller: it is up and nobody else claimed it.
+    pub fn is_up(&self, name: &str) -> bool {
+        self.statuses.get(name) == Some(&ControllerStatus::Up)
+    }
+
+    /// Whether any controller is up, i.e. whether there is anything to play to.
+    pub fn any_up(&self) -> bool {
+        self.statuses.values().any(|status| *status == ControllerStatus::Up)
+    }
+
+    pub fn any_claimed(&self) -> bool {
+        self.statuses.values().any(|status| matches!(status, ControllerStatus::Claimed(_)))
+    }
+
+    /// Whether the show should play under `policy`.
+    pub fn playable(&self, policy: ClaimPolicy) -> bool {
+        self.any_up() && !(policy == ClaimPolicy::Stop && self.any_claimed())
+    }
+
+    pub fn set(&mut self, name: &str, status: ControllerStatus) {
+        self.statuses.insert(name.to_string(), status);
+    }
+}
+
+// ----------------------------------------------------------------------
+// MonitorConfig Struct
+// ----------------------------------------------------------------------
+/// Longest the monitor sleeps before re-reading the streaming state.
+const STREAMING_POLL_GRANULARITY: Duration = Duration::from_secs(1);
+
+/// How often controllers are checked and how many results it takes to change their state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MonitorConfig {
+    /// Check interval while the player is not streaming.
+    pub idle_interval: Duration,
+    /// Check interval while the player is streaming, so dropouts are noticed quickly.
+    pub streaming_interval: Duration,
+    /// Consecutive failed checks before a controller that was up is reported down.
+    pub failure_threshold: u32,
+    /// Consecutive successful checks before a controller that went down is reported up again.
+    pub success_threshold: u32,
+    /// Cap for the interval between checks of a down controller, which doubles after every
+    /// failed check. A controller that comes back is reported up at worst `max_backoff` plus
+    /// `success_threshold - 1` intervals later: 90s with the defaults while idle.
+    pub max_backoff: Duration,
+    /// How to react when another source takes over a controller.
+    pub claim_policy: ClaimPolicy,
+    /// How long a controller stays claimed after a live override that lasts until realtime
+    /// data stops (`lor 1`) is cleared. WLED clears it as soon as the player yields, so
+    /// without this the player would take the controller straight back.
+    pub override_hold: Duration,
+}
+
+impl Default for MonitorConfig {
+    fn default() -> Self {
+        MonitorConfig {
+            idle_interval: Duration::from_secs(30),
+            streaming_interval: Duration::from_secs(5),
+            failure_threshold: 3,
+            success_threshold: 2,
+            max_backoff: Duration::from_secs(60),
+            claim_policy: ClaimPolicy::default(),
+            override_hold: Duration::from_secs(300),
+        }
+    }
+}
+
+/// Failure counting and check scheduling for one controller.
+#[derive(Debug, Default)]
+struct ControllerState {
+    up: bool,
+    /// Whether the controller has been up since the monitor started; the first success counts
+    /// as up straight away, only recovery needs `success_threshold` successes.
+    seen_up: bool,
+    consecutive_failures: u32,
+    consecutive_successes: u32,
+    /// Failed checks since the controller went down, for the backoff.
+    down_checks: u32,
+    /// When a `lor 1` override was last seen on the controller.
+    override_seen: Option<Instant>,
+    /// The controller did not answer WLED's JSON API, so it is not asked about claims again
+    /// until it goes down and comes back.
+    skip_claim_check: bool,
+}
+
+impl ControllerState {
+    /// Records a check result and returns the new up state if it changed.
+    fn record(&mut self, name: &str, is_up: bool, config: &MonitorConfig) -> Option<bool> {
+        if is_up {
+            // Device is UP, reset counter and report it if needed
+            if self.consecutive_failures > 0 {
+                log::info!("Monitor: {} UP. Cleared {} failures.", name, self.consecutive_failures);
+                self.consecutive_failures = 0;
+            }
+            if !self.up {
+                self.consecutive_successes += 1;
+                if self.seen_up && self.consecutive_successes < config.success_threshold {
+                    log::info!("Monitor: {} answered (Success {}/{})", name, self.consecutive_successes, config.success_threshold);
+                    return None;
+                }
+                self.up = true;
+                self.seen_up = true;
+                self.consecutive_successes = 0;
+                self.down_checks = 0;
+                return Some(true);
+            }
+        } else {
+            self.consecutive_successes = 0;
+            if self.up {
+                // Device is DOWN. Only count failures while it is up; otherwise we quietly poll.
+                self.consecutive_failures += 1;
+                log::error!("Monitor: {} DOWN (Failure {}/{})", name, self.consecutive_failures, config.failure_threshold);
+
+                if self.consecutive_failures >= config.failure_threshold {
+                    self.up = false;
+                    self.consecutive_failures = 0;
+                    self.override_seen = None;
+                    self.skip_claim_check = false;
+                    return Some(false);
+                }
+            } else {
+                self.down_checks = self.down_checks.saturating_add(1);
+            }
         }
+        None
+    }
+
+    /// The claim to report for a freshly read `claim`. A `lor 1` override is held for
+    /// `override_hold` after it was last seen, unless another claim replaces it.
+    fn latch_claim(&mut self, claim: Option<RealtimeClaim>, now: Instant, config: &MonitorConfig) -> Option<RealtimeClaim> {
+        match claim {
+            Some(RealtimeClaim::Override(1)) => {
+                self.override_seen = Some(now);
+                claim
+            }
+            Some(claim) => {
+                self.override_seen = None;
+                Some(claim)
+            }
+            None => match self.override_seen {
+                Some(seen) if now < seen + config.override_hold => Some(RealtimeClaim::Override(1)),
+                _ => {
+                    self.override_seen = None;
+                    None
+                }
+            },
+        }
+    }
+
+    /// Time until the next check. Down controllers back off exponentially up to `max_backoff`,
+    /// except while they are answering again and working towards the success threshold.
+    fn interval(&self, config: &MonitorConfig, streaming: bool) -> Duration {
+        let base = if streaming { config.streaming_interval } else { config.idle_interval };
+        if self.up || self.consecutive_successes > 0 {
+            return base;
+        }
+        let factor = 1u32 << self.down_checks.min(u32::BITS - 1);
+        base.saturating_mul(factor).min(config.max_backoff.max(base))
     }
 }
 
 // ----------------------------------------------------------------------
 // Monitor/Poller Thread Logic
 // ----------------------------------------------------------------------
-pub fn run_monitor_thread(config: PlaybackConfig, tx_stream_state: Sender<bool>) {
-    let monitor_ip = config.wled_ip_address.clone();
-    let monitor_tx = tx_stream_state;
-    
-    // We spawn a blocking thread since the system ping check is blocking and runs forever.
-    tokio::task::spawn_blocking(move || {
-        let mut consecutive_failures = 0;
-        const FAILURE_THRESHOLD: i32 = 3;
-        
-        log::info!("Monitor started. Polling {} every 30 seconds...", monitor_ip);
+/// Checks every controller in the map and publishes their status whenever it changes. Unless
+/// the claim policy is `Ignore`, controllers that are up are also asked over WLED's JSON API
+/// whether another source has taken over. The player keeps streaming to the controllers that
+/// are up, and reports on `rx_streaming` whether it is streaming at all.
+pub fn run_monitor_thread(config: PlaybackConfig, tx_health: Sender<ControllerHealth>, rx_streaming: Receiver<bool>) {
+    let monitor = config.monitor;
+    let controllers: Vec<(Controller, Box<dyn HealthProbe>)> = config
+        .controllers
+        .controllers
+        .into_iter()
+        .map(|controller| {
+            let probe = controller.probe.build(&controller);
+            (controller, probe)
+        })
+        .collect();
+
+    let names: Vec<String> = controllers.iter().map(|(controller, _)| format!("{} ({:?})", controller.name, controller.probe)).collect();
+    log::info!(
+        "Monitor started. Polling {} every {:?} while streaming and {:?} while idle...",
+        names.join(", "), monitor.streaming_interval, monitor.idle_interval
+    );
+
+    spawn_controller_monitors(controllers, monitor, tx_health, rx_streaming);
+}
+
+/// Starts one thread per controller, so a controller whose probe times out never delays the
+/// checks of the others. Each thread runs until every health receiver is dropped.
+fn spawn_controller_monitors(
+    controllers: Vec<(Controller, Box<dyn HealthProbe>)>,
+    monitor: MonitorConfig,
+    tx_health: Sender<ControllerHealth>,
+    rx_streaming: Receiver<bool>,
+) {
+    for (controller, probe) in controllers {
+        let tx_health = tx_health.clone();
+        let rx_streaming = rx_streaming.clone();
+        let name = controller.name.clone();
+        let spawn_result = thread::Builder::new()
+            .name(format!("monitor-{}", controller.name))
+            .spawn(move || monitor_controller(&controller, probe.as_ref(), &monitor, &tx_health, &rx_streaming));
+
+        if let Err(e) = spawn_result {
+            log::error!("Monitor: Failed to spawn the monitor thread for {}: {}", name, e);
+        }
+    }
+}
+
+/// Checks one controller whenever its interval is up and publishes its status changes.
+fn monitor_controller(
+    controller: &Controller,
+    probe: &dyn HealthProbe,
+    monitor: &MonitorConfig,
+    tx_health: &Sender<ControllerHealth>,
+    rx_streaming: &Receiver<bool>,
+) {
+    let mut state = ControllerState::default();
+    let mut status = ControllerStatus::Down;
+
+    while !tx_health.is_closed() {
+        // PERFORM CHECK (runs immediately on first iteration)
+        let last_check = Instant::now();
+        let new_status = check_controller(controller, probe, &mut state, monitor, last_check);
+        if new_status != status {
+            log_status_change(&controller.name, &status, &new_status, monitor.claim_policy);
+            status = new_status.clone();
+            tx_health.send_modify(|health| health.set(&controller.name, new_status));
+        }
 
+        // Sleep until the next check is due, waking regularly in case streaming starts or stops.
         loop {
-            // PERFORM CHECK (runs immediately on first iteration)
-            let is_up = check_device_status(&monitor_ip);
-
-            match is_up {
-                true => {
-                    // Device is UP, reset counter and send START if needed
-                    if consecutive_failures > 0 {
-                        log::info!("Monitor: Device UP. Cleared {} failures.", consecutive_failures);
-                        consecutive_failures = 0;
-                    }
-                    
-                    if !*monitor_tx.borrow() {
-                        log::info!("Monitor: Device is UP. Sending START signal to Player.");
-                        monitor_tx.send(true).unwrap_or_else(|e| log::error!("Monitor failed to send START signal: {}", e));
-                    }
-                }
-                false => {
-                    // Device is DOWN. Only process failures if we are actively streaming.
-                    if *monitor_tx.borrow() { 
-                        consecutive_failures += 1;
-                        
-                        log::error!("Monitor: Device DOWN (Failure {}/{})", consecutive_failures, FAILURE_THRESHOLD);
-
-                        // Check for STOP condition (hits 3 AND streaming).
-                        if consecutive_failures >= FAILURE_THRESHOLD {
-                            log::error!("Monitor: Hit failure threshold. Sending STOP signal to Player.");
-                            monitor_tx.send(false).unwrap_or_else(|e| log::error!("Monitor failed to send STOP signal: {}", e));
-                        }
-                    }
-                    // If we are not streaming, we quietly poll.
-                }
+            let next_check = last_check + state.interval(monitor, *rx_streaming.borrow());
+            let remaining = next_check.saturating_duration_since(Instant::now());
+            if remaining.is_zero() || tx_health.is_closed() {
+                break;
+            }
+            thread::sleep(remaining.min(STREAMING_POLL_GRANULARITY));
+        }
+    }
+}
+
+/// Probes the controller, and asks it about claims if it is up, returning its new status.
+fn check_controller(controller: &Controller, probe: &dyn HealthProbe, state: &mut ControllerState, monitor: &MonitorConfig, now: Instant) -> ControllerStatus {
+    let is_up = match probe.check() {
+        Ok(()) => true,
+        Err(e) => {
+            log::debug!("Monitor: {} probe failed: {:#}", controller.name, e);
+            false
+        }
+    };
+    state.record(&controller.name, is_up, monitor);
+
+    match (state.up, monitor.claim_policy) {
+        (false, _) => ControllerStatus::Down,
+        (true, ClaimPolicy::Ignore) => ControllerStatus::Up,
+        (true, _) if state.skip_claim_check => ControllerStatus::Up,
+        (true, _) => match check_claim(controller) {
+            Ok(claim) => match state.latch_claim(claim, now, monitor) {
+                Some(claim) => ControllerStatus::Claimed(claim),
+                None => ControllerStatus::Up,
+            },
+            Err(e) => {
+                // Not every controller runs WLED; one we cannot ask is never claimed.
+                log::info!("Monitor: Cannot read the realtime state of {}, not checking it for claims: {:#}", controller.name, e);
+                state.skip_claim_check = true;
+                ControllerStatus::Up
             }
-            thread::sleep(Duration::from_secs(30)); 
+        },
+    }
+}
+
+fn check_claim(controller: &Controller) -> Result<Option<RealtimeClaim>> {
+    let local_ip = local_ip_towards(&controller.host, controller.port)?;
+    query_realtime_claim(&controller.host, local_ip, PROBE_TIMEOUT)
+}
+
+fn log_status_change(name: &str, old: &ControllerStatus, new: &ControllerStatus, policy: ClaimPolicy) {
+    match (old, new) {
+        (_, ControllerStatus::Down) => log::error!("Monitor: {} hit the failure threshold. Skipping it until it returns.", name),
+        (ControllerStatus::Claimed(_), ControllerStatus::Up) => log::info!("Monitor: {} was released. Resuming output to it.", name),
+        (_, ControllerStatus::Up) => log::info!("Monitor: {} is UP. Resuming output to it.", name),
+        (_, ControllerStatus::Claimed(claim)) => {
+            let action = match policy {
+                ClaimPolicy::Stop => "Stopping the show until it is released.",
+                _ => "Yielding it until it is released.",
+            };
+            log::warn!("Monitor: {} is claimed by {}. {}", name, claim, action);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> MonitorConfig {
+        MonitorConfig {
+            idle_interval: Duration::from_secs(30),
+            streaming_interval: Duration::from_secs(5),
+            failure_threshold: 3,
+            success_threshold: 2,
+            max_backoff: Duration::from_secs(100),
+            claim_policy: ClaimPolicy::Yield,
+            override_hold: Duration::from_secs(60),
+        }
+    }
+
+    #[test]
+    fn controllers_go_down_after_the_failure_threshold() {
+        let config = config();
+        let mut state = ControllerState::default();
+        // Failures before the first success are not counted, and the first success is enough.
+        assert_eq!(state.record("tree", false, &config), None);
+        assert_eq!(state.record("tree", true, &config), Some(true));
+
+        assert_eq!(state.record("tree", false, &config), None);
+        assert_eq!(state.record("tree", true, &config), None);
+        assert_eq!(state.record("tree", false, &config), None);
+        assert_eq!(state.record("tree", false, &config), None);
+        assert_eq!(state.record("tree", false, &config), Some(false));
+        assert_eq!(state.record("tree", false, &config), None);
+
+        // Recovery takes two successes in a row.
+        assert_eq!(state.record("tree", true, &config), None);
+        assert_eq!(state.record("tree", false, &config), None);
+        assert_eq!(state.record("tree", true, &config), None);
+        assert_eq!(state.record("tree", true, &config), Some(true));
+    }
+
+    #[test]
+    fn polls_faster_while_streaming_and_backs_off_while_down() {
+        let config = config();
+        let mut state = ControllerState::default();
+        state.record("tree", true, &config);
+        assert_eq!(state.interval(&config, true), Duration::from_secs(5));
+        assert_eq!(state.interval(&config, false), Duration::from_secs(30));
+
+        for _ in 0..3 {
+            state.record("tree", false, &config);
+        }
+        assert_eq!(state.interval(&config, false), Duration::from_secs(30));
+        state.record("tree", false, &config);
+        assert_eq!(state.interval(&config, false), Duration::from_secs(60));
+        assert_eq!(state.interval(&config, true), Duration::from_secs(10));
+        state.record("tree", false, &config);
+        assert_eq!(state.interval(&config, false), Duration::from_secs(100));
+        // While other controllers stream, the shorter interval backs off to the same cap.
+        state.record("tree", false, &config);
+        assert_eq!(state.interval(&config, true), Duration::from_secs(40));
+        for _ in 0..100 {
+            state.record("tree", false, &config);
+        }
+        assert_eq!(state.interval(&config, true), Duration::from_secs(100));
+
+        // Once it answers again, it is checked at the normal rate until it counts as up.
+        state.record("tree", true, &config);
+        assert_eq!(state.interval(&config, false), Duration::from_secs(30));
+    }
+
+    #[test]
+    fn backoff_is_only_limited_by_max_backoff() {
+        let config = MonitorConfig { max_backoff: Duration::from_secs(600), ..config() };
+        let mut state = ControllerState::default();
+        state.record("tree", true, &config);
+        for _ in 0..3 {
+            state.record("tree", false, &config);
+        }
+
+        // 30s, 60s, 120s, 240s, 480s, then the 10 minute cap: far more than 4 intervals.
+        let intervals: Vec<u64> = (0..6)
+            .map(|_| {
+                state.record("tree", false, &config);
+                state.interval(&config, false).as_secs()
+            })
+            .collect();
+        assert_eq!(intervals, vec![60, 120, 240, 480, 600, 600]);
+    }
+
+    #[test]
+    fn holds_overrides_that_clear_when_the_player_yields() {
+        let config = config();
+        let mut state = ControllerState::default();
+        let seen = Instant::now();
+
+        // lor 1 clears once our stream stops; the claim holds anyway.
+        assert_eq!(state.latch_claim(Some(RealtimeClaim::Override(1)), seen, &config), Some(RealtimeClaim::Override(1)));
+        assert_eq!(state.latch_claim(None, seen + Duration::from_secs(30), &config), Some(RealtimeClaim::Override(1)));
+        assert_eq!(state.latch_claim(None, seen + Duration::from_secs(60), &config), None);
+        assert_eq!(state.latch_claim(None, seen + Duration::from_secs(61), &config), None);
+
+        // Another claim replaces the held override, and a released controller is not held.
+        state.latch_claim(Some(RealtimeClaim::Override(1)), seen, &config);
+        let other = RealtimeClaim::OtherSource { mode: "E1.31".to_string(), source: "192.168.1.20".to_string() };
+        assert_eq!(state.latch_claim(Some(other.clone()), seen, &config), Some(other));
+        assert_eq!(state.latch_claim(None, seen + Duration::from_secs(1), &config), None);
+
+        // Going down drops the held override.
+        state.record("tree", true, &config);
+        state.latch_claim(Some(RealtimeClaim::Override(1)), seen, &config);
+        for _ in 0..3 {
+            state.record("tree", false, &config);
+        }
+        assert_eq!(state.latch_claim(None, seen + Duration::from_secs(1), &config), None);
+    }
+
+    /// A probe that answers after `delay`.
+    struct SlowProbe {
+        delay: Duration,
+        up: bool,
+    }
+
+    impl HealthProbe for SlowProbe {
+        fn check(&self) -> Result<()> {
+            thread::sleep(self.delay);
+            if self.up { Ok(()) } else { Err(anyhow::anyhow!("timed out")) }
         }
-    });
+    }
+
+    #[test]
+    fn slow_controllers_do_not_delay_the_others() {
+        let config = MonitorConfig { claim_policy: ClaimPolicy::Ignore, ..config() };
+        let controllers: Vec<(Controller, Box<dyn HealthProbe>)> = vec![
+            (Controller { name: "roofline".to_string(), ..Controller::whole_show("10.0.0.1", 4048) }, Box::new(SlowProbe { delay: Duration::from_secs(1), up: false })),
+            (Controller { name: "tree".to_string(), ..Controller::whole_show("10.0.0.2", 4048) }, Box::new(SlowProbe { delay: Duration::from_secs(1), up: false })),
+            (Controller { name: "garage".to_string(), ..Controller::whole_show("10.0.0.3", 4048) }, Box::new(SlowProbe { delay: Duration::ZERO, up: true })),
+        ];
+        let (tx_health, rx_health) = tokio::sync::watch::channel(ControllerHealth::default());
+        let (_tx_streaming, rx_streaming) = tokio::sync::watch::channel(true);
+
+        let started = Instant::now();
+        spawn_controller_monitors(controllers, config, tx_health, rx_streaming);
+        while !rx_health.borrow().is_up("garage") {
+            assert!(started.elapsed() < Duration::from_millis(500), "garage was checked after the timed out controllers");
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert!(!rx_health.borrow().is_up("roofline") && !rx_health.borrow().is_up("tree"));
+    }
+
+    #[test]
+    fn health_is_tracked_per_controller() {
+        let mut health = ControllerHealth::default();
+        assert!(!health.any_up());
+        health.set("roofline", ControllerStatus::Up);
+        health.set("tree", ControllerStatus::Down);
+        assert!(health.any_up());
+        assert!(health.is_up("roofline"));
+        assert!(!health.is_up("tree"));
+        assert!(!health.is_up("unknown"));
+
+        // A claimed controller is skipped, and only stops the show under the stop policy.
+        health.set("tree", ControllerStatus::Claimed(RealtimeClaim::Override(1)));
+        assert!(!health.is_up("tree"));
+        assert!(health.playable(ClaimPolicy::Yield));
+        assert!(!health.playable(ClaimPolicy::Stop));
+        health.set("roofline", ControllerStatus::Down);
+        assert!(!health.playable(ClaimPolicy::Yield));
+    }
 }
@@ -0,0 +1,304 @@
+// src/output.rs
+use anyhow::{Context, Result, bail};
+use ddp_rs::connection::DDPConnection;
+use ddp_rs::protocol::{self, DataType, PixelConfig};
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::path::Path;
+use tokio::sync::watch::Receiver;
+use crate::monitor::ControllerHealth;
+use crate::probe::ProbeKind;
+
+/// Standard DDP port, used for controllers that do not set one.
+pub const DDP_PORT: u16 = 4048;
+
+// ----------------------------------------------------------------------
+// PixelFormat Enum
+// ----------------------------------------------------------------------
+/// Pixel layout announced in the DDP header sent to a controller.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PixelFormat {
+    /// 3 channels per pixel.
+    #[default]
+    Rgb,
+    /// 4 channels per pixel.
+    Rgbw,
+    /// 1 channel per pixel.
+    Grayscale,
+}
+
+impl PixelFormat {
+    pub fn pixel_config(self) -> PixelConfig {
+        let (data_type, data_size) = match self {
+            PixelFormat::Rgb => (DataType::RGB, protocol::PixelFormat::Pixel24Bits),
+            PixelFormat::Rgbw => (DataType::RGBW, protocol::PixelFormat::Pixel32Bits),
+            PixelFormat::Grayscale => (DataType::Grayscale, protocol::PixelFormat::Pixel8Bits),
+        };
+        PixelConfig { data_type, data_size, customer_defined: false }
+    }
+}
+
+// ----------------------------------------------------------------------
+// Controller Struct
+// ----------------------------------------------------------------------
+/// A DDP controller and the part of the sequence's channel space it owns.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Controller {
+    pub name: String,
+    pub host: String,
+    pub port: u16,
+    /// First sequence channel (0-based) sent to this controller, at its DDP offset 0.
+    pub start_channel: u32,
+    pub channel_count: u32,
+    pub pixel_format: PixelFormat,
+    /// How the monitor checks that the controller is up.
+    pub probe: ProbeKind,
+}
+
+impl Controller {
+    /// A controller that receives the whole channel space at the sequence's own offsets.
+    pub fn whole_show(host: &str, port: u16) -> Self {
+        Controller {
+            name: host.to_string(),
+            host: host.to_string(),
+            port,
+            start_channel: 0,
+            channel_count: u32::MAX,
+            pixel_format: PixelFormat::default(),
+            probe: ProbeKind::default(),
+        }
+    }
+
+    /// One past the last sequence channel this controller owns.
+    pub fn end_channel(&self) -> u32 {
+        self.start_channel.saturating_add(self.channel_count)
+    }
+
+    /// The part of `data`, starting at sequence channel `start_channel`, that falls in this
+    /// controller's range, with its DDP offset on the controller.
+    pub fn slice<'a>(&self, start_channel: u32, data: &'a [u8]) -> Option<(u32, &'a [u8])> {
+        let data_end = start_channel.saturating_add(data.len() as u32);
+        let from = start_channel.max(self.start_channel);
+        let to = data_end.min(self.end_channel());
+        if from >= to {
+            return None;
+        }
+        Some((from - self.start_channel, &data[(from - start_channel) as usize..(to - start_channel) as usize]))
+    }
+}
+
+// ----------------------------------------------------------------------
+// ControllerMap Struct
+// ----------------------------------------------------------------------
+/// The controllers a show is split across.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ControllerMap {
+    pub controllers: Vec<Controller>,
+}
+
+impl ControllerMap {
+    /// A single controller receiving the whole show, checked with `probe`.
+    pub fn single(host: &str, port: u16, probe: ProbeKind) -> Self {
+        ControllerMap { controllers: vec![Controller { probe, ..Controller::whole_show(host, port) }] }
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read controller map {}", path.display()))?;
+        Self::parse_json(&text).with_context(|| format!("Invalid controller map {}", path.display()))
+    }
+
+    /// Parses a JSON controller map:
+    ///
+    /// ```json
+    /// { "controllers": [{ "name": "roofline", "host": "192.168.1.50", "port": 4048,
+    ///                     "start_channel": 0, "channel_count": 1500, "pixel_format": "rgb", "probe": "http" }] }
+    /// ```
+    ///
+    /// `name` defaults to the host, `port` to 4048, `pixel_format` to `rgb` and `probe` to `icmp`.
+    pub fn parse_json(text: &str) -> Result<Self> {
+        let document: JsonControllerMap = serde_json::from_str(text)?;
+        if document.controllers.is_empty() {
+            bail!("Controller map has no controllers");
+        }
+
+        let mut names = HashSet::new();
+        let controllers = document
+            .controllers
+            .into_iter()
+            .map(|controller| {
+                let controller = Controller {
+                    name: controller.name.unwrap_or_else(|| controller.host.clone()),
+                    host: controller.host,
+                    port: controller.port.unwrap_or(DDP_PORT),
+                    start_channel: controller.start_channel,
+                    channel_count: controller.channel_count,
+                    pixel_format: controller.pixel_format,
+                    probe: controller.probe,
+                };
+                if controller.channel_count == 0 {
+                    bail!("Controller {:?} has no channels", controller.name);
+                }
+                if !names.insert(controller.name.clone()) {
+                    bail!("Controller name {:?} is used more than once", controller.name);
+                }
+                Ok(controller)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(ControllerMap { controllers })
+    }
+}
+
+#[derive(Deserialize)]
+struct JsonControllerMap {
+    controllers: Vec<JsonController>,
+}
+
+#[derive(Deserialize)]
+struct JsonController {
+    name: Option<String>,
+    host: String,
+    port: Option<u16>,
+    start_channel: u32,
+    channel_count: u32,
+    #[serde(default)]
+    pixel_format: PixelFormat,
+    #[serde(default)]
+    probe: ProbeKind,
+}
+
+// ----------------------------------------------------------------------
+// Output Struct
+// ----------------------------------------------------------------------
+/// Open DDP connections to every controller in a map. Each frame is sliced by channel range
+/// and every controller gets its part in the same frame tick.
+pub struct Output {
+    connections: Vec<(Controller, DDPConnection)>,
+    /// Monitor health; controllers it reports down are skipped.
+    rx_health: Option<Receiver<ControllerHealth>>,
+    /// Whether each connection is sent to, refreshed when the health changes.
+    enabled: Vec<bool>,
+}
+
+impl Output {
+    pub fn connect(map: &ControllerMap) -> Result<Self> {
+        let connections = map
+            .controllers
+            .iter()
+            .map(|controller| {
+                let local_socket = UdpSocket::bind(SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), 0))
+                    .context("Failed to bind local UDP socket")?;
+                let conn = DDPConnection::try_new(
+                    format!("{}:{}", controller.host, controller.port),
+                    controller.pixel_format.pixel_config(),
+                    protocol::ID::Default,
+                    local_socket,
+                )
+                .with_context(|| format!("Failed to open DDP connection to {}", controller.name))?;
+                Ok((controller.clone(), conn))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let enabled = vec![true; connections.len()];
+        Ok(Output { connections, rx_health: None, enabled })
+    }
+
+    /// Only sends to the controllers `rx_health` reports up.
+    pub fn with_health(mut self, rx_health: Receiver<ControllerHealth>) -> Self {
+        self.rx_health = Some(rx_health);
+        self.refresh_health(true);
+        self
+    }
+
+    fn refresh_health(&mut self, force: bool) {
+        let Some(rx_health) = &mut self.rx_health else {
+            return;
+        };
+        if !force && !rx_health.has_changed().unwrap_or(false) {
+            return;
+        }
+
+        let health = rx_health.borrow_and_update();
+        for ((controller, _), enabled) in self.connections.iter().zip(&mut self.enabled) {
+            let up = health.is_up(&controller.name);
+            if up != *enabled && !force {
+                if up {
+                    log::info!("Output: {} is back, sending to it again.", controller.name);
+                } else {
+                    log::info!("Output: {} is {}, skipping it.", controller.name, health.status(&controller.name));
+                }
+            }
+            *enabled = up;
+        }
+    }
+
+    /// Sends the channels of `data`, which starts at sequence channel `start_channel`, to the
+    /// controllers owning them.
+    pub fn send(&mut self, start_channel: u32, data: &[u8]) {
+        self.refresh_health(false);
+        for ((controller, conn), &enabled) in self.connections.iter_mut().zip(&self.enabled) {
+            if !enabled {
+                continue;
+            }
+            let Some((offset, slice)) = controller.slice(start_channel, data) else {
+                continue;
+            };
+            if let Err(e) = conn.write_offset(slice, offset) {
+                log::error!("Error sending DDP packet to {} (channel {}): {}", controller.name, offset, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn controller(start_channel: u32, channel_count: u32) -> Controller {
+        Controller { start_channel, channel_count, ..Controller::whole_show("10.0.0.1", DDP_PORT) }
+    }
+
+    #[test]
+    fn slices_segments_to_controller_ranges() {
+        let data: Vec<u8> = (0..30).collect();
+        let roof = controller(100, 12);
+
+        // A segment at channels 90..120 overlaps the roofline's 100..112 at its offsets 0..12.
+        assert_eq!(roof.slice(90, &data), Some((0, &data[10..22])));
+        // A segment starting inside the range lands at its offset within the controller.
+        assert_eq!(roof.slice(105, &data[..3]), Some((5, &data[..3])));
+        assert_eq!(roof.slice(112, &data), None);
+        assert_eq!(roof.slice(70, &data), None);
+
+        // The whole-show controller keeps the sequence's own offsets.
+        assert_eq!(Controller::whole_show("10.0.0.1", DDP_PORT).slice(90, &data), Some((90, &data[..])));
+    }
+
+    #[test]
+    fn parses_controller_maps() {
+        let map = ControllerMap::parse_json(
+            r#"{ "controllers": [
+                { "name": "roofline", "host": "10.0.0.1", "start_channel": 0, "channel_count": 1500 },
+                { "host": "10.0.0.2", "port": 4049, "start_channel": 1500, "channel_count": 800, "pixel_format": "rgbw", "probe": "ddp" }
+            ] }"#,
+        )
+        .unwrap();
+
+        assert_eq!(map.controllers[0].port, DDP_PORT);
+        assert_eq!(map.controllers[0].pixel_format, PixelFormat::Rgb);
+        assert_eq!(map.controllers[1].name, "10.0.0.2");
+        assert_eq!((map.controllers[1].port, map.controllers[1].end_channel()), (4049, 2300));
+        assert_eq!(map.controllers[1].pixel_format, PixelFormat::Rgbw);
+        assert_eq!((map.controllers[0].probe, map.controllers[1].probe), (ProbeKind::Icmp, ProbeKind::Ddp));
+
+        assert!(ControllerMap::parse_json(r#"{ "controllers": [] }"#).is_err());
+        assert!(ControllerMap::parse_json(r#"{ "controllers": [{ "host": "a", "start_channel": 0, "channel_count": 0 }] }"#).is_err());
+        assert!(ControllerMap::parse_json(
+            r#"{ "controllers": [{ "host": "a", "start_channel": 0, "channel_count": 3 }, { "host": "a", "start_channel": 3, "channel_count": 3 }] }"#
+        )
+        .is_err());
+    }
+}
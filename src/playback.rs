@@ -1,69 +1,731 @@
 // src/playback.rs
 use anyhow::{Result, bail};
-use std::thread::sleep;
-use std::time::Duration;
-use std::sync::Arc; 
+use std::path::Path;
+use std::thread::{self, JoinHandle, sleep};
+use std::time::{Duration, Instant};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
 use tokio::sync::watch::Receiver;
-use ddp_rs::connection::DDPConnection; 
-use crate::fseq_ffi::FseqFile;
+use crate::BLANK_REFRESH_INTERVAL;
+use crate::fseq::FseqFile;
+use crate::fseq_storage::LoadMode;
+use crate::frame_clock::{DriftStats, FrameClock};
+use crate::output::Output;
+use crate::playlist::{Playlist, PlaylistEntry};
 
+/// Longest the player sleeps between stop checks while waiting out a gap or a late start.
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// ----------------------------------------------------------------------
+// SequenceLoader Struct
+// ----------------------------------------------------------------------
+/// Opens playlist entries with the configured storage and block cache settings.
+#[derive(Debug, Clone, Copy)]
+pub struct SequenceLoader {
+    pub load_mode: LoadMode,
+    pub block_cache_bytes: usize,
+    pub prefetch_enabled: bool,
+}
+
+impl SequenceLoader {
+    pub fn open(&self, path: &Path) -> Result<FseqFile> {
+        let fseq_file = FseqFile::open(path, self.load_mode)?
+            .with_prefetch(self.prefetch_enabled)
+            .with_block_cache_budget(self.block_cache_bytes);
+        if let Some(media_filename) = fseq_file.get_media_filename() {
+            log::info!("Sequence media file: {}", media_filename);
+        }
+        Ok(fseq_file)
+    }
+}
+
+// ----------------------------------------------------------------------
+// SequenceTiming Struct
+// ----------------------------------------------------------------------
+/// Length and frame rate of a playlist entry, read up front so resume points can be computed
+/// across the whole playlist without opening every sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SequenceTiming {
+    pub frame_count: u32,
+    pub step: Duration,
+    /// One past the highest DDP channel the sequence writes; blanking this many channels
+    /// turns off everything it lights.
+    pub channel_extent: u32,
+}
+
+impl SequenceTiming {
+    /// Reads the timing from the sequence header, rejecting sequences that cannot be played.
+    pub fn probe(path: &Path) -> Result<Self> {
+        // Streaming only reads the header region, however large the file is.
+        let fseq_file = FseqFile::open(path, LoadMode::Stream)?;
+        let frame_count = fseq_file.get_frame_count();
+        let step_time_ms = fseq_file.get_step_time();
+
+        if frame_count == 0 {
+            bail!("Sequence {} has no frames", path.display());
+        }
+        if step_time_ms <= 0 {
+            bail!("Sequence {} has an invalid step time of {}ms", path.display(), step_time_ms);
+        }
+
+        let blank = vec![0u8; fseq_file.frame_size()];
+        let channel_extent = fseq_file
+            .frame_segments(&blank)
+            .map(|segment| segment.start_channel + segment.data.len() as u32)
+            .max()
+            .unwrap_or(0);
+
+        Ok(SequenceTiming { frame_count, step: Duration::from_millis(step_time_ms as u64), channel_extent })
+    }
+
+    /// Playing time of `loops` back to back runs.
+    fn play_nanos(&self, loops: u32) -> u128 {
+        self.step.as_nanos() * self.frame_count as u128 * loops as u128
+    }
+}
+
+// ----------------------------------------------------------------------
+// PlaybackCursor Struct
+// ----------------------------------------------------------------------
+/// A position in a playlist pass and the instant it is due.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlaybackCursor {
+    /// Entry indices in the order of the current pass.
+    pub order: Vec<usize>,
+    /// Index into `order` of the entry being played.
+    pub slot: usize,
+    /// Completed runs of the current entry.
+    pub run: u32,
+    pub frame: u32,
+    /// When `frame` is due.
+    pub due_at: Instant,
+    /// When frame 0 of the first entry in this pass was due.
+    pub pass_start: Instant,
+}
+
+impl PlaybackCursor {
+    /// The start of a pass in `order` beginning at `at`.
+    pub fn start(order: Vec<usize>, at: Instant) -> Self {
+        PlaybackCursor { order, slot: 0, run: 0, frame: 0, due_at: at, pass_start: at }
+    }
+}
+
+// ----------------------------------------------------------------------
+// ResumePolicy Enum
+// ----------------------------------------------------------------------
+/// Where playback picks up when the monitor sends START after a STOP.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ResumePolicy {
+    /// Start the playlist again from its first entry.
+    #[default]
+    Restart,
+    /// Continue from the first frame that was not sent before the STOP.
+    LastFrame,
+    /// Continue from where the playlist would be had it never stopped.
+    WallClock,
+}
+
+impl ResumePolicy {
+    /// Where to start playing `playlist`, whose entries have the given `timings`.
+    ///
+    /// For `WallClock` the cursor may be due in the past; it is the frame whose slot contains
+    /// `now`, so the playback clock keeps the original phase. Inside a gap it is the start of
+    /// the next entry, due in the future.
+    pub fn resume_point(
+        self,
+        position: &PlaybackPosition,
+        playlist: &Playlist,
+        timings: &[SequenceTiming],
+        loop_enabled: bool,
+        now: Instant,
+    ) -> PlaybackCursor {
+        let restart = || PlaybackCursor::start(playlist.play_order(), now);
+        let state = position.lock_state();
+        let Some(pass_start) = state.pass_start else {
+            return restart();
+        };
+
+        match self {
+            ResumePolicy::Restart => restart(),
+            ResumePolicy::LastFrame => PlaybackCursor {
+                order: state.order.clone(),
+                slot: state.slot,
+                run: state.run,
+                frame: state.next_frame,
+                due_at: now,
+                pass_start,
+            },
+            ResumePolicy::WallClock => {
+                let spans: Vec<(u128, u128)> = state
+                    .order
+                    .iter()
+                    .map(|&index| (timings[index].play_nanos(playlist.entries[index].loops), playlist.entries[index].gap.as_nanos()))
+                    .collect();
+                let pass_nanos: u128 = spans.iter().map(|(play, gap)| play + gap).sum();
+                if pass_nanos == 0 {
+                    return restart();
+                }
+
+                let mut pass_start = pass_start;
+                let mut elapsed = now.saturating_duration_since(pass_start).as_nanos();
+                if elapsed >= pass_nanos {
+                    if !loop_enabled {
+                        // The show would already have finished; play it again from the top.
+                        return restart();
+                    }
+                    let passes = elapsed / pass_nanos;
+                    pass_start += nanos(passes * pass_nanos);
+                    elapsed -= passes * pass_nanos;
+                }
+
+                let mut offset = 0u128;
+                for (slot, (&index, (play, gap))) in state.order.iter().zip(spans).enumerate() {
+                    if elapsed < offset + play {
+                        let timing = timings[index];
+                        let step = timing.step.as_nanos();
+                        let slots_played = (elapsed - offset) / step;
+                        return PlaybackCursor {
+                            order: state.order.clone(),
+                            slot,
+                            run: (slots_played / timing.frame_count as u128) as u32,
+                            frame: (slots_played % timing.frame_count as u128) as u32,
+                            due_at: pass_start + nanos(offset + slots_played * step),
+                            pass_start,
+                        };
+                    }
+
+                    offset += play + gap;
+                    if elapsed < offset {
+                        // Inside the gap after this entry; wait for the next one.
+                        return PlaybackCursor {
+                            order: state.order.clone(),
+                            slot: slot + 1,
+                            run: 0,
+                            frame: 0,
+                            due_at: pass_start + nanos(offset),
+                            pass_start,
+                        };
+                    }
+                }
+
+                restart()
+            }
+        }
+    }
+}
+
+fn nanos(value: u128) -> Duration {
+    Duration::from_nanos(value as u64)
+}
+
+// ----------------------------------------------------------------------
+// PlaybackPosition Struct
+// ----------------------------------------------------------------------
+/// Playback progress shared across the playback loops started by one `play_sequence` call,
+/// so a loop started after a STOP/START cycle can resume where the previous one left off.
+#[derive(Debug, Default)]
+pub struct PlaybackPosition {
+    state: Mutex<PositionState>,
+}
+
+#[derive(Debug, Default)]
+struct PositionState {
+    order: Vec<usize>,
+    slot: usize,
+    run: u32,
+    next_frame: u32,
+    /// When the current pass started; `None` until something has played.
+    pass_start: Option<Instant>,
+}
+
+impl PlaybackPosition {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The first frame not yet sent by the most recent playback loop.
+    pub fn next_frame(&self) -> u32 {
+        self.lock_state().next_frame
+    }
+
+    fn begin_pass(&self, cursor: &PlaybackCursor) {
+        let mut state = self.lock_state();
+        state.order = cursor.order.clone();
+        state.pass_start = Some(cursor.pass_start);
+        state.slot = cursor.slot;
+        state.run = cursor.run;
+        state.next_frame = cursor.frame;
+    }
+
+    fn set_cursor(&self, cursor: &PlaybackCursor) {
+        let mut state = self.lock_state();
+        state.slot = cursor.slot;
+        state.run = cursor.run;
+        state.next_frame = cursor.frame;
+    }
+
+    fn reset(&self) {
+        *self.lock_state() = PositionState::default();
+    }
+
+    /// A panicked playback thread leaves the position as it was; the next loop resumes from it.
+    fn lock_state(&self) -> MutexGuard<'_, PositionState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+// ----------------------------------------------------------------------
+// Playback Loop
+// ----------------------------------------------------------------------
 pub struct PlaybackContext {
-    pub fseq_file: Arc<FseqFile>, 
+    pub playlist: Arc<Playlist>,
+    /// Timing of each playlist entry, indexed like `playlist.entries`.
+    pub timings: Arc<Vec<SequenceTiming>>,
+    pub loader: SequenceLoader,
+    /// Repeat the playlist after its last entry.
     pub loop_enabled: bool,
+    /// Where to start, normally from `ResumePolicy::resume_point`.
+    pub start: PlaybackCursor,
+    pub position: Arc<PlaybackPosition>,
 }
 
+/// Plays the playlist to `output` until it finishes or the monitor signals STOP.
+///
+/// Entries are played back to back on one frame clock, so switching sequences costs no time
+/// and the DDP connections stay open for the whole show.
 pub fn run_playback_loop(
-    mut conn: DDPConnection, 
+    output: Output,
     context: PlaybackContext,
-    rx_stream_state: Receiver<bool>, 
+    rx_stream_state: Receiver<bool>,
 ) -> Result<()> {
-    
-    let frame_count = context.fseq_file.get_frame_count();
-    let step_time_ms = context.fseq_file.get_step_time();
-    let step_duration = Duration::from_millis(step_time_ms as u64);
-    let mut frame_num = 0u32;
-    let mut sequence_run_count = 0;
-
-    log::info!("Player: Playback started ({} frames @ {}ms).", frame_count, step_time_ms);
-
-    loop {
-        // --- Frame Retrieval and Sending Logic ---
-        match context.fseq_file.get_frame(frame_num) {
-            Ok(frame_data) => {
-                if let Err(e) = conn.write(&frame_data) {
-                    log::error!("Error sending DDP packet for frame {}: {}", frame_num, e);
+    let cursor = context.start.clone();
+    let mut player = PlaylistPlayer { output, context, rx_stream_state, cursor, pass_count: 0, next_open: None, next_order: None };
+    player.run()
+}
+
+struct PlaylistPlayer {
+    output: Output,
+    context: PlaybackContext,
+    rx_stream_state: Receiver<bool>,
+    cursor: PlaybackCursor,
+    pass_count: u32,
+    /// The entry after the current one, being opened in the background.
+    next_open: Option<PendingOpen>,
+    /// Order of the next pass, drawn early so its first entry can be opened ahead of time.
+    next_order: Option<Vec<usize>>,
+}
+
+struct PendingOpen {
+    entry_index: usize,
+    handle: JoinHandle<Result<FseqFile>>,
+}
+
+impl PlaylistPlayer {
+    fn run(&mut self) -> Result<()> {
+        let playlist = self.context.playlist.clone();
+        // The open sequence is kept while consecutive slots play the same entry, e.g. a looping single file.
+        let mut current: Option<(usize, Arc<FseqFile>)> = None;
+        self.context.position.begin_pass(&self.cursor);
+
+        loop {
+            self.normalize_cursor();
+
+            if self.cursor.slot >= self.cursor.order.len() {
+                self.pass_count += 1;
+                log::info!("✅ Playlist COMPLETED. Total passes: {}\n", self.pass_count);
+
+                if !self.context.loop_enabled {
+                    self.context.position.reset();
+                    return Ok(());
+                }
+                // The next pass starts where this one ended, after the last entry's gap.
+                let order = self.next_order.take().unwrap_or_else(|| playlist.play_order());
+                self.cursor = PlaybackCursor::start(order, self.cursor.due_at);
+                self.context.position.begin_pass(&self.cursor);
+            }
+
+            let entry_index = self.cursor.order[self.cursor.slot];
+            let entry = &playlist.entries[entry_index];
+            let fseq_file = match &current {
+                Some((index, fseq_file)) if *index == entry_index => fseq_file.clone(),
+                _ => {
+                    let fseq_file = Arc::new(self.open_entry(entry_index)?);
+                    current = Some((entry_index, fseq_file.clone()));
+                    fseq_file
                 }
+            };
+            self.open_next_in_background(entry_index);
+
+            if !self.play_entry(&fseq_file, entry)? {
+                return Ok(());
+            }
+            if !entry.gap.is_zero() && !self.hold_gap(&fseq_file, entry.gap) {
+                return Ok(());
             }
-            Err(e) => {
-                bail!("Error retrieving frame {}: {}", frame_num, e);
+
+            self.cursor.slot += 1;
+            self.cursor.run = 0;
+            self.cursor.frame = 0;
+            self.context.position.set_cursor(&self.cursor);
+        }
+    }
+
+    /// Opens a playlist entry, taking it from the background open if one was started for it.
+    fn open_entry(&mut self, entry_index: usize) -> Result<FseqFile> {
+        match self.next_open.take() {
+            Some(pending) if pending.entry_index == entry_index => {
+                pending.handle.join().unwrap_or_else(|panic| std::panic::resume_unwind(panic))
             }
+            _ => self.context.loader.open(&self.context.playlist.entries[entry_index].path),
         }
+    }
 
-        // --- Frame Management and Stop Check ---
-        frame_num += 1;
-        
-        if frame_num >= frame_count {
-            sequence_run_count += 1;
-            
-            // ⭐️ Logging sequence completion 
-            log::info!("✅ Sequence COMPLETED. Total runs: {}\n", sequence_run_count);
-            
-            if context.loop_enabled {
+    /// Starts opening the entry after the current one on another thread, so switching to it
+    /// does not wait for its header to be read or the file to be loaded into memory.
+    fn open_next_in_background(&mut self, current_index: usize) {
+        let next_index = match self.cursor.order.get(self.cursor.slot + 1) {
+            Some(&index) => index,
+            None if self.context.loop_enabled => {
+                let playlist = &self.context.playlist;
+                match self.next_order.get_or_insert_with(|| playlist.play_order()).first() {
+                    Some(&index) => index,
+                    None => return,
+                }
+            }
+            None => return,
+        };
+        if next_index == current_index || self.next_open.as_ref().is_some_and(|pending| pending.entry_index == next_index) {
+            return;
+        }
+
+        let loader = self.context.loader;
+        let path = self.context.playlist.entries[next_index].path.clone();
+        let spawn_result = thread::Builder::new()
+            .name("sequence-open".to_string())
+            .spawn(move || {
+                let fseq_file = loader.open(&path)?;
+                // Have the first block decoded by the time the entry starts.
+                fseq_file.prefetch_block(0);
+                Ok(fseq_file)
+            });
+
+        match spawn_result {
+            Ok(handle) => self.next_open = Some(PendingOpen { entry_index: next_index, handle }),
+            // The entry is then opened when it is reached.
+            Err(e) => log::warn!("Player: Failed to spawn a thread to open the next sequence: {}", e),
+        }
+    }
+
+    /// Moves a cursor that points past the end of an entry's frames or loops to the next entry.
+    fn normalize_cursor(&mut self) {
+        let Some(&entry_index) = self.cursor.order.get(self.cursor.slot) else {
+            return;
+        };
+        if self.cursor.frame >= self.context.timings[entry_index].frame_count {
+            self.cursor.frame = 0;
+            self.cursor.run += 1;
+        }
+        if self.cursor.run >= self.context.playlist.entries[entry_index].loops {
+            self.cursor.slot += 1;
+            self.cursor.run = 0;
+            self.cursor.frame = 0;
+        }
+    }
+
+    /// Plays the remaining runs of the entry at the cursor. Returns `false` on a STOP signal.
+    fn play_entry(&mut self, fseq_file: &FseqFile, entry: &PlaylistEntry) -> Result<bool> {
+        let frame_count = fseq_file.get_frame_count();
+        let step_time_ms = fseq_file.get_step_time();
+        let step_duration = Duration::from_millis(step_time_ms as u64);
+        let mut frame_num = self.cursor.frame.min(frame_count.saturating_sub(1));
+        // Reused for every frame so the loop does not allocate while streaming.
+        let mut frame_data = vec![0u8; fseq_file.frame_size()];
+        // Consecutive frames skipped because of recoverable decode errors; only the first is logged.
+        let mut skipped_frames = 0u32;
+        // Frame deadlines come from a fixed origin, so decode and send time never accumulates as drift.
+        let mut clock = FrameClock::new(step_duration, self.cursor.due_at, frame_num);
+        let mut drift_stats = DriftStats::default();
+
+        let entry_name = entry.path.file_name().map_or_else(|| entry.path.display().to_string(), |name| name.to_string_lossy().into_owned());
+        if frame_num > 0 || self.cursor.run > 0 {
+            log::info!(
+                "Player: Playback of {} resumed at frame {}, run {} of {} ({} frames @ {}ms).",
+                entry_name, frame_num, self.cursor.run + 1, entry.loops, frame_count, step_time_ms
+            );
+        } else {
+            log::info!("Player: Playback of {} started ({} frames @ {}ms).", entry_name, frame_count, step_time_ms);
+        }
+
+        loop {
+            if !self.wait_until(clock.deadline(frame_num)) {
+                log::info!("Player: Stop signal received from Monitor. Halting DDP stream.");
+                log::debug!("Player: Timing for partial run: {}", drift_stats);
+                return Ok(false);
+            }
+
+            // --- Frame Scheduling ---
+            // When a whole step or more behind, jump to the frame that is due now instead of slowing down.
+            let due_frame = clock.due_frame(Instant::now()).min(frame_count.saturating_sub(1));
+            if due_frame > frame_num {
+                log::debug!("Player: Behind schedule, skipping frames {}-{}.", frame_num, due_frame - 1);
+                drift_stats.record_skipped(due_frame - frame_num);
+                frame_num = due_frame;
+            }
+
+            // --- Frame Retrieval and Sending Logic ---
+            match fseq_file.read_frame_into(frame_num, &mut frame_data) {
+                Ok(()) => {
+                    if skipped_frames > 0 {
+                        log::info!("Player: Frame {} decoded again after {} skipped frames.", frame_num, skipped_frames);
+                        skipped_frames = 0;
+                    }
+
+                    self.send_frame(fseq_file, &frame_data);
+                    drift_stats.record_sent(Instant::now().saturating_duration_since(clock.deadline(frame_num)));
+                }
+                Err(e) if e.is_recoverable() => {
+                    // A bad block only affects its own frames; keep the clock running and hold the last output.
+                    if skipped_frames == 0 {
+                        log::warn!("Player: Skipping undecodable frame {}: {}", frame_num, e);
+                    }
+                    skipped_frames += 1;
+                }
+                Err(e) => {
+                    bail!("Error retrieving frame {} of {}: {}", frame_num, entry_name, e);
+                }
+            }
+
+            // --- Frame Management ---
+            frame_num += 1;
+
+            if frame_num >= frame_count {
+                self.cursor.run += 1;
+
+                // ⭐️ Logging sequence completion
+                log::info!("✅ Sequence COMPLETED: {} (run {} of {}).", entry_name, self.cursor.run, entry.loops);
+                if drift_stats.has_drift() {
+                    log::info!("Player: Timing for run {}: {}", self.cursor.run, drift_stats);
+                } else {
+                    log::debug!("Player: Timing for run {}: {}", self.cursor.run, drift_stats);
+                }
+                drift_stats = DriftStats::default();
+
+                // The next run or entry starts exactly one step after the last frame of this one.
+                let run_end = clock.deadline(frame_num);
+                if self.cursor.run >= entry.loops {
+                    self.cursor.frame = frame_num;
+                    self.cursor.due_at = run_end;
+                    self.context.position.set_cursor(&self.cursor);
+                    return Ok(true);
+                }
+                clock.rebase(0, run_end);
                 frame_num = 0;
-            } else {
-                break;
             }
+
+            self.cursor.frame = frame_num;
+            self.context.position.set_cursor(&self.cursor);
         }
-        
-        if rx_stream_state.has_changed().is_ok() {
-            if !*rx_stream_state.borrow() {
+    }
+
+    /// Blanks the channels of the entry that just finished and waits out its gap, resending the
+    /// blank frame every `BLANK_REFRESH_INTERVAL` so the controller stays in realtime mode.
+    /// Returns `false` on a STOP signal.
+    fn hold_gap(&mut self, fseq_file: &FseqFile, gap: Duration) -> bool {
+        log::info!("Player: Pausing {:?} before the next sequence.", gap);
+        let blank = vec![0u8; fseq_file.frame_size()];
+        self.cursor.due_at += gap;
+
+        let mut next_refresh = Instant::now();
+        loop {
+            self.send_frame(fseq_file, &blank);
+            next_refresh += BLANK_REFRESH_INTERVAL;
+            let wake_at = next_refresh.min(self.cursor.due_at);
+            if !self.wait_until(wake_at) {
                 log::info!("Player: Stop signal received from Monitor. Halting DDP stream.");
-                return Ok(());
+                return false;
+            }
+            if wake_at == self.cursor.due_at {
+                return true;
             }
         }
+    }
 
-        sleep(step_duration);
+    fn send_frame(&mut self, fseq_file: &FseqFile, frame_data: &[u8]) {
+        // Sparse files carry several channel ranges per frame; each goes to its own channels.
+        for segment in fseq_file.frame_segments(frame_data) {
+            self.output.send(segment.start_channel, segment.data);
+        }
     }
 
-    Ok(())
+    /// Sleeps until `deadline`, checking for a STOP signal at least every `STOP_POLL_INTERVAL`.
+    /// Returns `false` if playback should stop, including when the sender of the run flag is
+    /// gone because the show itself ended.
+    fn wait_until(&self, deadline: Instant) -> bool {
+        loop {
+            match self.rx_stream_state.has_changed() {
+                Ok(_) if *self.rx_stream_state.borrow() => {}
+                _ => return false,
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return true;
+            }
+            sleep(remaining.min(STOP_POLL_INTERVAL));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fseq::CompressionType;
+    use crate::output::ControllerMap;
+    use crate::probe::ProbeKind;
+    use crate::test_data::{self, TempDir, V2Fixture};
+    use std::net::UdpSocket;
+
+    const STEP: Duration = Duration::from_millis(25);
+
+    /// Three entries of 200, 100 and 40 frames; the second plays twice with a 1s gap after it.
+    fn show() -> (Playlist, Vec<SequenceTiming>) {
+        let mut playlist = Playlist::from_files(["intro.fseq", "song.fseq", "outro.fseq"]);
+        playlist.entries[1] = PlaylistEntry::new("song.fseq").with_loops(2).with_gap(Duration::from_secs(1));
+        let timings = [200, 100, 40].map(|frame_count| SequenceTiming { frame_count, step: STEP, channel_extent: 30 }).to_vec();
+        (playlist, timings)
+    }
+
+    fn stopped_at(slot: usize, run: u32, next_frame: u32, pass_start: Instant) -> PlaybackPosition {
+        let position = PlaybackPosition::new();
+        position.begin_pass(&PlaybackCursor { order: vec![0, 1, 2], slot, run, frame: next_frame, due_at: pass_start, pass_start });
+        position
+    }
+
+    #[test]
+    fn restart_and_last_frame_resume_points() {
+        let (playlist, timings) = show();
+        let now = Instant::now();
+        let pass_start = now - STEP * 400;
+        let position = stopped_at(1, 1, 60, pass_start);
+
+        assert_eq!(ResumePolicy::Restart.resume_point(&position, &playlist, &timings, true, now), PlaybackCursor::start(vec![0, 1, 2], now));
+
+        let cursor = ResumePolicy::LastFrame.resume_point(&position, &playlist, &timings, true, now);
+        assert_eq!((cursor.slot, cursor.run, cursor.frame, cursor.due_at, cursor.pass_start), (1, 1, 60, now, pass_start));
+        assert_eq!(position.next_frame(), 60);
+
+        // Nothing has played yet.
+        let cursor = ResumePolicy::LastFrame.resume_point(&PlaybackPosition::new(), &playlist, &timings, true, now);
+        assert_eq!(cursor, PlaybackCursor::start(vec![0, 1, 2], now));
+    }
+
+    #[test]
+    fn wall_clock_resume_keeps_the_original_phase() {
+        let (playlist, timings) = show();
+        let pass_start = Instant::now();
+        let position = stopped_at(0, 0, 10, pass_start);
+
+        // 330 slots in is frame 30 of the second run of the song (200 + 100 + 30).
+        let now = pass_start + STEP * 330 + Duration::from_millis(7);
+        let cursor = ResumePolicy::WallClock.resume_point(&position, &playlist, &timings, true, now);
+        assert_eq!((cursor.slot, cursor.run, cursor.frame), (1, 1, 30));
+        assert_eq!(cursor.due_at, pass_start + STEP * 330);
+
+        // Inside the gap after the song, the outro is due when the gap ends.
+        let now = pass_start + STEP * 410;
+        let cursor = ResumePolicy::WallClock.resume_point(&position, &playlist, &timings, true, now);
+        assert_eq!((cursor.slot, cursor.run, cursor.frame), (2, 0, 0));
+        assert_eq!(cursor.due_at, pass_start + STEP * 400 + Duration::from_secs(1));
+
+        // A pass is 440 slots plus the 1s gap; the second pass keeps the phase.
+        let pass_length = STEP * 440 + Duration::from_secs(1);
+        let now = pass_start + pass_length + STEP * 5;
+        let cursor = ResumePolicy::WallClock.resume_point(&position, &playlist, &timings, true, now);
+        assert_eq!((cursor.slot, cursor.run, cursor.frame), (0, 0, 5));
+        assert_eq!(cursor.pass_start, pass_start + pass_length);
+
+        // Without looping the show would have finished, so it plays again from the top.
+        let cursor = ResumePolicy::WallClock.resume_point(&position, &playlist, &timings, false, now);
+        assert_eq!(cursor, PlaybackCursor::start(vec![0, 1, 2], now));
+    }
+
+    /// An output to a local socket, and the socket.
+    fn local_output() -> (Output, UdpSocket) {
+        let receiver = UdpSocket::bind("127.0.0.1:0").unwrap();
+        receiver.set_read_timeout(Some(Duration::from_millis(200))).unwrap();
+        let port = receiver.local_addr().unwrap().port();
+        (Output::connect(&ControllerMap::single("127.0.0.1", port, ProbeKind::default())).unwrap(), receiver)
+    }
+
+    /// The DDP payloads received so far; each frame fits one packet after its 10 byte header.
+    fn received_payloads(receiver: &UdpSocket) -> Vec<Vec<u8>> {
+        let mut payloads = Vec::new();
+        let mut packet = [0u8; 1500];
+        while let Ok(len) = receiver.recv(&mut packet) {
+            payloads.push(packet[10..len].to_vec());
+        }
+        payloads
+    }
+
+    fn player_context(playlist: Playlist, position: Arc<PlaybackPosition>) -> PlaybackContext {
+        let timings = playlist.entries.iter().map(|entry| SequenceTiming::probe(&entry.path).unwrap()).collect();
+        let order = (0..playlist.entries.len()).collect();
+        PlaybackContext {
+            playlist: Arc::new(playlist),
+            timings: Arc::new(timings),
+            loader: SequenceLoader { load_mode: LoadMode::Auto, block_cache_bytes: 1 << 20, prefetch_enabled: true },
+            loop_enabled: false,
+            start: PlaybackCursor::start(order, Instant::now()),
+            position,
+        }
+    }
+
+    #[test]
+    fn plays_entry_loops_and_transitions() {
+        let directory = TempDir::new("playlist-player");
+        let intro = directory.file("intro.fseq");
+        let outro = directory.file("outro.fseq");
+        std::fs::write(&intro, V2Fixture::new(CompressionType::Zstd, 3, 12, 2).build()).unwrap();
+        std::fs::write(&outro, V2Fixture::new(CompressionType::None, 2, 9, 0).build()).unwrap();
+
+        let gap = STEP * 4;
+        let playlist = Playlist {
+            entries: vec![PlaylistEntry::new(&intro).with_loops(2), PlaylistEntry::new(&outro).with_gap(gap)],
+            shuffle: false,
+        };
+
+        let (output, receiver) = local_output();
+        let (_tx_stream_state, rx_stream_state) = tokio::sync::watch::channel(true);
+        let position = Arc::new(PlaybackPosition::new());
+        let started = Instant::now();
+        run_playback_loop(output, player_context(playlist, position.clone()), rx_stream_state).unwrap();
+        assert!(started.elapsed() >= STEP * 7 + gap);
+
+        let mut payloads = received_payloads(&receiver);
+
+        let mut expected: Vec<Vec<u8>> = [0, 1, 2, 0, 1, 2].map(|frame| test_data::frame_data(frame, 12)).to_vec();
+        expected.extend([0, 1].map(|frame| test_data::frame_data(frame, 9)));
+        assert_eq!(payloads[..expected.len()], expected[..]);
+        // The gap is blanked at least once; how often it is refreshed depends on timing.
+        let blanks = payloads.split_off(expected.len());
+        assert!(!blanks.is_empty());
+        assert!(blanks.iter().all(|blank| *blank == [0u8; 9]));
+        assert_eq!(position.next_frame(), 0);
+    }
+
+    #[test]
+    fn stops_when_the_run_flag_sender_is_gone() {
+        let directory = TempDir::new("playlist-player-orphaned");
+        let sequence = directory.file("show.fseq");
+        std::fs::write(&sequence, V2Fixture::new(CompressionType::None, 400, 9, 0).build()).unwrap();
+
+        let (output, receiver) = local_output();
+        let (tx_stream_state, rx_stream_state) = tokio::sync::watch::channel(true);
+        drop(tx_stream_state);
+        let context = player_context(Playlist::from_files([&sequence]), Arc::new(PlaybackPosition::new()));
+
+        // The 10s sequence is abandoned straight away instead of playing on its own.
+        let started = Instant::now();
+        run_playback_loop(output, context, rx_stream_state).unwrap();
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert!(received_payloads(&receiver).is_empty());
+    }
 }
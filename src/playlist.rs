@@ -0,0 +1,251 @@
+// src/playlist.rs
+use anyhow::{Context, Result, bail};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// M3U directive setting how many times the next entry plays.
+const M3U_LOOPS_DIRECTIVE: &str = "#EXTWLED-LOOPS:";
+/// M3U directive setting the pause after the next entry, e.g. `#EXTWLED-GAP:2s`.
+const M3U_GAP_DIRECTIVE: &str = "#EXTWLED-GAP:";
+/// M3U directive that shuffles the whole playlist.
+const M3U_SHUFFLE_DIRECTIVE: &str = "#EXTWLED-SHUFFLE";
+
+// ----------------------------------------------------------------------
+// PlaylistEntry Struct
+// ----------------------------------------------------------------------
+/// One sequence in a playlist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlaylistEntry {
+    pub path: PathBuf,
+    /// How many times the sequence plays back to back before the next entry.
+    pub loops: u32,
+    /// Pause with the lights blanked after the entry's last loop.
+    pub gap: Duration,
+}
+
+impl PlaylistEntry {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        PlaylistEntry { path: path.into(), loops: 1, gap: Duration::ZERO }
+    }
+
+    pub fn with_loops(mut self, loops: u32) -> Self {
+        self.loops = loops;
+        self
+    }
+
+    pub fn with_gap(mut self, gap: Duration) -> Self {
+        self.gap = gap;
+        self
+    }
+}
+
+// ----------------------------------------------------------------------
+// Playlist Struct
+// ----------------------------------------------------------------------
+/// An ordered list of sequences played over one DDP connection.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Playlist {
+    pub entries: Vec<PlaylistEntry>,
+    /// Play the entries in a new random order on every pass.
+    pub shuffle: bool,
+}
+
+impl Playlist {
+    /// A playlist of `paths` in order, each played once.
+    pub fn from_files<I, P>(paths: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        Playlist { entries: paths.into_iter().map(PlaylistEntry::new).collect(), shuffle: false }
+    }
+
+    /// Loads an M3U (`.m3u`, `.m3u8`) or JSON (`.json`) playlist. Relative entry paths are
+    /// resolved against the playlist's directory.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read playlist {}", path.display()))?;
+        let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+
+        let extension = path.extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase);
+        let playlist = match extension.as_deref() {
+            Some("m3u") | Some("m3u8") => Self::parse_m3u(&text, base_dir),
+            Some("json") => Self::parse_json(&text, base_dir),
+            _ => bail!("Unknown playlist format for {} (expected .m3u, .m3u8 or .json)", path.display()),
+        };
+        playlist.with_context(|| format!("Invalid playlist {}", path.display()))
+    }
+
+    /// Parses an M3U playlist. Besides the standard comment lines it understands
+    /// `#EXTWLED-LOOPS:<n>` and `#EXTWLED-GAP:<duration>`, which apply to the next entry,
+    /// and `#EXTWLED-SHUFFLE`.
+    pub fn parse_m3u(text: &str, base_dir: &Path) -> Result<Self> {
+        let mut playlist = Playlist::default();
+        let mut loops = 1;
+        let mut gap = Duration::ZERO;
+
+        for (line_index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            let line_number = line_index + 1;
+
+            if let Some(value) = line.strip_prefix(M3U_LOOPS_DIRECTIVE) {
+                loops = value.trim().parse().with_context(|| format!("line {}: invalid loop count {:?}", line_number, value))?;
+            } else if let Some(value) = line.strip_prefix(M3U_GAP_DIRECTIVE) {
+                gap = parse_gap(value).with_context(|| format!("line {}: invalid gap", line_number))?;
+            } else if line == M3U_SHUFFLE_DIRECTIVE {
+                playlist.shuffle = true;
+            } else if !line.is_empty() && !line.starts_with('#') {
+                playlist.entries.push(PlaylistEntry::new(base_dir.join(line)).with_loops(loops).with_gap(gap));
+                loops = 1;
+                gap = Duration::ZERO;
+            }
+        }
+
+        playlist.validate()?;
+        Ok(playlist)
+    }
+
+    /// Parses a JSON playlist: either an array of entries or an object with `entries`,
+    /// optional `shuffle` and a default `gap`. Entries are paths or objects with `file`,
+    /// optional `loops` and optional `gap` (e.g. `"1.5s"`).
+    pub fn parse_json(text: &str, base_dir: &Path) -> Result<Self> {
+        let document: JsonPlaylist = serde_json::from_str(text)?;
+        let (entries, shuffle, default_gap) = match document {
+            JsonPlaylist::Entries(entries) => (entries, false, None),
+            JsonPlaylist::Document { entries, shuffle, gap } => (entries, shuffle, gap),
+        };
+        let default_gap = default_gap.as_deref().map(parse_gap).transpose()?.unwrap_or(Duration::ZERO);
+
+        let entries = entries
+            .into_iter()
+            .map(|entry| match entry {
+                JsonEntry::Path(path) => Ok(PlaylistEntry::new(base_dir.join(path)).with_gap(default_gap)),
+                JsonEntry::Entry { file, loops, gap } => {
+                    let gap = gap.as_deref().map(parse_gap).transpose()?.unwrap_or(default_gap);
+                    Ok(PlaylistEntry::new(base_dir.join(file)).with_loops(loops.unwrap_or(1)).with_gap(gap))
+                }
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let playlist = Playlist { entries, shuffle };
+        playlist.validate()?;
+        Ok(playlist)
+    }
+
+    pub fn with_shuffle(mut self, shuffle: bool) -> Self {
+        self.shuffle = shuffle;
+        self
+    }
+
+    /// Sets the pause after every entry.
+    pub fn with_gap(mut self, gap: Duration) -> Self {
+        for entry in &mut self.entries {
+            entry.gap = gap;
+        }
+        self
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.entries.is_empty() {
+            bail!("Playlist has no entries");
+        }
+        if let Some(entry) = self.entries.iter().find(|entry| entry.loops == 0) {
+            bail!("Loop count for {} must be at least 1", entry.path.display());
+        }
+        Ok(())
+    }
+
+    /// Entry indices in the order of one pass through the playlist, shuffled if enabled.
+    pub fn play_order(&self) -> Vec<usize> {
+        let mut order: Vec<usize> = (0..self.entries.len()).collect();
+        if self.shuffle {
+            fastrand::shuffle(&mut order);
+        }
+        order
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonPlaylist {
+    Entries(Vec<JsonEntry>),
+    Document {
+        entries: Vec<JsonEntry>,
+        #[serde(default)]
+        shuffle: bool,
+        gap: Option<String>,
+    },
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonEntry {
+    Path(String),
+    Entry {
+        file: String,
+        loops: Option<u32>,
+        gap: Option<String>,
+    },
+}
+
+/// Parses a gap such as `2s`, `500ms` or `1m 30s`.
+pub fn parse_gap(value: &str) -> Result<Duration> {
+    humantime::parse_duration(value.trim()).with_context(|| format!("invalid duration {:?}", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_m3u_directives() {
+        let text = "#EXTM3U\n#EXTWLED-SHUFFLE\nintro.fseq\n\n#EXTINF:180,Song\n#EXTWLED-LOOPS:2\n#EXTWLED-GAP:1.5s\nsongs/song.fseq\n/abs/outro.fseq\n";
+        let playlist = Playlist::parse_m3u(text, Path::new("/show")).unwrap();
+
+        assert!(playlist.shuffle);
+        assert_eq!(
+            playlist.entries,
+            vec![
+                PlaylistEntry::new("/show/intro.fseq"),
+                PlaylistEntry::new("/show/songs/song.fseq").with_loops(2).with_gap(Duration::from_millis(1500)),
+                PlaylistEntry::new("/abs/outro.fseq"),
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_json_playlists() {
+        let text = r#"{ "gap": "2s", "entries": ["intro.fseq", { "file": "song.fseq", "loops": 3, "gap": "0s" }] }"#;
+        let playlist = Playlist::parse_json(text, Path::new("/show")).unwrap();
+
+        assert!(!playlist.shuffle);
+        assert_eq!(
+            playlist.entries,
+            vec![
+                PlaylistEntry::new("/show/intro.fseq").with_gap(Duration::from_secs(2)),
+                PlaylistEntry::new("/show/song.fseq").with_loops(3),
+            ]
+        );
+
+        let playlist = Playlist::parse_json(r#"["a.fseq", "b.fseq"]"#, Path::new("")).unwrap();
+        assert_eq!(playlist, Playlist::from_files(["a.fseq", "b.fseq"]));
+    }
+
+    #[test]
+    fn rejects_invalid_playlists() {
+        assert!(Playlist::parse_m3u("#EXTM3U\n", Path::new("")).is_err());
+        assert!(Playlist::parse_m3u("#EXTWLED-LOOPS:0\na.fseq\n", Path::new("")).is_err());
+        assert!(Playlist::parse_m3u("#EXTWLED-GAP:soon\na.fseq\n", Path::new("")).is_err());
+        assert!(Playlist::parse_json(r#"{ "entries": [{ "loops": 2 }] }"#, Path::new("")).is_err());
+    }
+
+    #[test]
+    fn shuffles_every_entry_once() {
+        let playlist = Playlist::from_files((0..20).map(|i| format!("{}.fseq", i))).with_shuffle(true);
+        let mut order = playlist.play_order();
+        order.sort_unstable();
+        assert_eq!(order, (0..20).collect::<Vec<_>>());
+        assert_eq!(playlist.with_shuffle(false).play_order(), (0..20).collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,319 @@
+// src/probe.rs
+use anyhow::{Context, Result, anyhow, bail};
+use serde::Deserialize;
+use serde_json::Value;
+use std::fmt;
+use std::io::{Read, Write};
+use std::net::{IpAddr, SocketAddr, TcpStream, ToSocketAddrs, UdpSocket};
+use std::process::Command;
+use std::time::Duration;
+use crate::output::Controller;
+
+/// How long a probe waits for a controller to answer.
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Port of the WLED web server, used by the HTTP and TCP probes.
+pub const HTTP_PORT: u16 = 80;
+
+/// DDP header flags byte for a version 1 query.
+const DDP_QUERY_FLAGS: u8 = 0x40 | 0x02;
+/// DDP id of the status resource.
+const DDP_STATUS_ID: u8 = 251;
+
+// ----------------------------------------------------------------------
+// HealthProbe Trait
+// ----------------------------------------------------------------------
+/// Checks whether a controller is up. Probes block for at most about their timeout.
+pub trait HealthProbe: Send {
+    /// `Ok` if the controller answered; the error says why it is considered down.
+    fn check(&self) -> Result<()>;
+}
+
+// ----------------------------------------------------------------------
+// ProbeKind Enum
+// ----------------------------------------------------------------------
+/// Which probe a controller is checked with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ProbeKind {
+    /// ICMP echo through the system `ping` binary; proves the host is up.
+    #[default]
+    Icmp,
+    /// `GET /json/info` on port 80; proves WLED is running.
+    Http,
+    /// TCP connect to port 80; no `ping` binary needed.
+    Tcp,
+    /// DDP status query on the controller's DDP port; proves something answers DDP.
+    Ddp,
+}
+
+impl ProbeKind {
+    pub fn build(self, controller: &Controller) -> Box<dyn HealthProbe> {
+        let host = controller.host.clone();
+        match self {
+            ProbeKind::Icmp => Box::new(IcmpProbe { host }),
+            ProbeKind::Http => Box::new(HttpProbe { host, port: HTTP_PORT, timeout: PROBE_TIMEOUT }),
+            ProbeKind::Tcp => Box::new(TcpProbe { host, port: HTTP_PORT, timeout: PROBE_TIMEOUT }),
+            ProbeKind::Ddp => Box::new(DdpProbe { host, port: controller.port, timeout: PROBE_TIMEOUT }),
+        }
+    }
+}
+
+fn resolve(host: &str, port: u16) -> Result<SocketAddr> {
+    (host, port)
+        .to_socket_addrs()
+        .with_context(|| format!("cannot resolve {}", host))?
+        .next()
+        .ok_or_else(|| anyhow!("{} has no address", host))
+}
+
+/// A UDP socket connected to `host`.
+fn connect_udp(host: &str, port: u16) -> Result<UdpSocket> {
+    let target = resolve(host, port)?;
+    let bind_addr: SocketAddr = if target.is_ipv4() { "0.0.0.0:0" } else { "[::]:0" }.parse()?;
+    let socket = UdpSocket::bind(bind_addr)?;
+    socket.connect(target)?;
+    Ok(socket)
+}
+
+// ----------------------------------------------------------------------
+// Probe Implementations
+// ----------------------------------------------------------------------
+pub struct IcmpProbe {
+    pub host: String,
+}
+
+impl HealthProbe for IcmpProbe {
+    fn check(&self) -> Result<()> {
+        // Uses the system's 'ping' command, configured to send 1 packet and timeout quickly (1 second).
+        let output = if cfg!(target_os = "windows") {
+            Command::new("ping").args(["-n", "1", "-w", "1000", &self.host]).output()
+        } else {
+            Command::new("ping").args(["-c", "1", "-W", "1", &self.host]).output()
+        };
+
+        let output = output.context("failed to execute system ping command (is 'ping' in PATH?)")?;
+        if !output.status.success() {
+            bail!("no ping reply");
+        }
+        Ok(())
+    }
+}
+
+pub struct HttpProbe {
+    pub host: String,
+    pub port: u16,
+    pub timeout: Duration,
+}
+
+impl HealthProbe for HttpProbe {
+    fn check(&self) -> Result<()> {
+        get_json(&self.host, self.port, "/json/info", self.timeout)?;
+        Ok(())
+    }
+}
+
+/// Fetches a JSON document from the WLED web server, failing on anything but a 200 with JSON.
+fn get_json(host: &str, port: u16, path: &str, timeout: Duration) -> Result<Value> {
+    let mut stream = TcpStream::connect_timeout(&resolve(host, port)?, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+
+    // HTTP/1.0 keeps the response unchunked and closes the connection after it.
+    write!(stream, "GET {} HTTP/1.0\r\nHost: {}\r\n\r\n", path, host)?;
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response)?;
+
+    let response = String::from_utf8_lossy(&response);
+    let (head, body) = response.split_once("\r\n\r\n").context("incomplete HTTP response")?;
+    let status = head.lines().next().unwrap_or_default();
+    if status.split_whitespace().nth(1) != Some("200") {
+        bail!("{} returned {:?}", path, status);
+    }
+    serde_json::from_str(body).with_context(|| format!("{} did not return JSON", path))
+}
+
+pub struct TcpProbe {
+    pub host: String,
+    pub port: u16,
+    pub timeout: Duration,
+}
+
+impl HealthProbe for TcpProbe {
+    fn check(&self) -> Result<()> {
+        TcpStream::connect_timeout(&resolve(&self.host, self.port)?, self.timeout)?;
+        Ok(())
+    }
+}
+
+pub struct DdpProbe {
+    pub host: String,
+    pub port: u16,
+    pub timeout: Duration,
+}
+
+impl HealthProbe for DdpProbe {
+    fn check(&self) -> Result<()> {
+        let socket = connect_udp(&self.host, self.port)?;
+        socket.set_read_timeout(Some(self.timeout))?;
+
+        // Header only: flags, sequence, data type, id, then a zero offset and length.
+        socket.send(&[DDP_QUERY_FLAGS, 0, 0, DDP_STATUS_ID, 0, 0, 0, 0, 0, 0])?;
+        let mut reply = [0u8; 1500];
+        let len = socket.recv(&mut reply).context("no reply to DDP status query")?;
+        if len < 10 || reply[0] & 0xc0 != 0x40 {
+            bail!("invalid DDP reply");
+        }
+        Ok(())
+    }
+}
+
+// ----------------------------------------------------------------------
+// WLED Realtime State
+// ----------------------------------------------------------------------
+/// Why a WLED controller is ignoring or about to ignore our frames.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RealtimeClaim {
+    /// Live data override (`lor`) set from the WLED app or API; 1 lasts until realtime data
+    /// stops, 2 until reboot.
+    Override(u64),
+    /// Another realtime source is streaming to the controller.
+    OtherSource { mode: String, source: String },
+}
+
+impl fmt::Display for RealtimeClaim {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RealtimeClaim::Override(lor) => write!(f, "live override (lor {}) set in WLED", lor),
+            RealtimeClaim::OtherSource { mode, source } if source.is_empty() => write!(f, "{} realtime data", mode),
+            RealtimeClaim::OtherSource { mode, source } => write!(f, "{} realtime data from {}", mode, source),
+        }
+    }
+}
+
+/// Reads WLED's `/json/state` and `/json/info` to find out whether something other than
+/// `local_ip` has claimed the controller's realtime mode.
+pub fn query_realtime_claim(host: &str, local_ip: IpAddr, timeout: Duration) -> Result<Option<RealtimeClaim>> {
+    let state = get_json(host, HTTP_PORT, "/json/state", timeout)?;
+    let info = get_json(host, HTTP_PORT, "/json/info", timeout)?;
+    Ok(realtime_claim(&state, &info, local_ip))
+}
+
+/// The claim described by WLED's `state` and `info` documents, if any. Realtime data from
+/// `local_ip` is our own.
+pub fn realtime_claim(state: &Value, info: &Value, local_ip: IpAddr) -> Option<RealtimeClaim> {
+    let lor = state.get("lor").and_then(Value::as_u64).unwrap_or(0);
+    if lor != 0 {
+        return Some(RealtimeClaim::Override(lor));
+    }
+
+    let live = info.get("live").and_then(Value::as_bool).unwrap_or(false);
+    let source = info.get("lip").and_then(Value::as_str).unwrap_or_default();
+    if live && source.parse::<IpAddr>().ok() != Some(local_ip) {
+        let mode = info.get("lm").and_then(Value::as_str).filter(|mode| !mode.is_empty()).unwrap_or("unknown");
+        return Some(RealtimeClaim::OtherSource { mode: mode.to_string(), source: source.to_string() });
+    }
+    None
+}
+
+/// The local address packets to `host` are sent from, which WLED reports as the realtime
+/// source while we stream.
+pub fn local_ip_towards(host: &str, port: u16) -> Result<IpAddr> {
+    Ok(connect_udp(host, port)?.local_addr()?.ip())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    const TEST_TIMEOUT: Duration = Duration::from_millis(500);
+
+    /// Serves one HTTP request with `response` on a local port.
+    fn http_stand_in(response: &'static str) -> u16 {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0u8; 1024];
+            let _ = stream.read(&mut request);
+            stream.write_all(response.as_bytes()).unwrap();
+        });
+        port
+    }
+
+    fn http_probe(port: u16) -> HttpProbe {
+        HttpProbe { host: "127.0.0.1".to_string(), port, timeout: TEST_TIMEOUT }
+    }
+
+    /// A local port with nothing listening on it.
+    fn closed_port() -> u16 {
+        TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+    }
+
+    #[test]
+    fn http_probe_requires_json_info() {
+        let port = http_stand_in("HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\r\n{\"ver\":\"0.15.0\",\"leds\":{\"count\":300}}");
+        assert!(http_probe(port).check().is_ok());
+
+        let port = http_stand_in("HTTP/1.1 404 Not Found\r\n\r\nnot found");
+        assert!(http_probe(port).check().is_err());
+
+        // Something else answering on port 80, such as a router page.
+        let port = http_stand_in("HTTP/1.1 200 OK\r\n\r\n<html></html>");
+        assert!(http_probe(port).check().is_err());
+
+        assert!(http_probe(closed_port()).check().is_err());
+    }
+
+    #[test]
+    fn detects_realtime_claims() {
+        let local_ip: IpAddr = "192.168.1.10".parse().unwrap();
+        let state = |lor: u64| serde_json::json!({ "on": true, "lor": lor });
+        let info = |live: bool, lip: &str| serde_json::json!({ "live": live, "lm": if live { "DDP" } else { "" }, "lip": lip });
+
+        assert_eq!(realtime_claim(&state(0), &info(false, ""), local_ip), None);
+        // Our own stream is not a claim.
+        assert_eq!(realtime_claim(&state(0), &info(true, "192.168.1.10"), local_ip), None);
+        assert_eq!(
+            realtime_claim(&state(0), &info(true, "192.168.1.20"), local_ip),
+            Some(RealtimeClaim::OtherSource { mode: "DDP".to_string(), source: "192.168.1.20".to_string() })
+        );
+        assert_eq!(realtime_claim(&state(1), &info(true, "192.168.1.10"), local_ip), Some(RealtimeClaim::Override(1)));
+        // Controllers without these fields are never claimed.
+        assert_eq!(realtime_claim(&serde_json::json!({}), &serde_json::json!({}), local_ip), None);
+    }
+
+    #[test]
+    fn tcp_probe_connects() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        assert!(TcpProbe { host: "127.0.0.1".to_string(), port, timeout: TEST_TIMEOUT }.check().is_ok());
+        drop(listener);
+        assert!(TcpProbe { host: "127.0.0.1".to_string(), port: closed_port(), timeout: TEST_TIMEOUT }.check().is_err());
+    }
+
+    #[test]
+    fn ddp_probe_sends_a_status_query() {
+        let stand_in = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = stand_in.local_addr().unwrap().port();
+        let probe = DdpProbe { host: "127.0.0.1".to_string(), port, timeout: TEST_TIMEOUT };
+
+        let responder = thread::spawn(move || {
+            let mut query = [0u8; 64];
+            let (len, from) = stand_in.recv_from(&mut query).unwrap();
+            assert_eq!((len, query[0], query[3]), (10, DDP_QUERY_FLAGS, DDP_STATUS_ID));
+            let reply = br#"{"status":{"man":"WLED"}}"#;
+            let mut packet = vec![0x44, 0, 0, DDP_STATUS_ID, 0, 0, 0, 0, 0, reply.len() as u8];
+            packet.extend_from_slice(reply);
+            stand_in.send_to(&packet, from).unwrap();
+            stand_in
+        });
+        assert!(probe.check().is_ok());
+
+        // The stand-in no longer answers.
+        let _silent = responder.join().unwrap();
+        assert!(probe.check().is_err());
+    }
+}
@@ -0,0 +1,510 @@
+// src/scheduler.rs
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, TimeZone, Weekday};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::watch::Sender;
+use crate::playlist::{Playlist, parse_gap};
+use crate::solar::{Location, SunEvent};
+
+/// How often the scheduler re-evaluates the windows against the local clock.
+const SCHEDULE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+// ----------------------------------------------------------------------
+// OutsideBehavior Enum
+// ----------------------------------------------------------------------
+/// What the player does while no window is active.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutsideBehavior {
+    /// Keep streaming black frames so the lights stay off.
+    #[default]
+    Blank,
+    /// Stop streaming and let the controller fall back to its own presets.
+    Idle,
+}
+
+// ----------------------------------------------------------------------
+// DateRange Struct
+// ----------------------------------------------------------------------
+/// A yearly range of days, inclusive at both ends. Ranges such as November 27 to January 6
+/// wrap around the new year.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateRange {
+    /// `(month, day)` of the first day.
+    pub from: (u32, u32),
+    /// `(month, day)` of the last day.
+    pub to: (u32, u32),
+}
+
+impl DateRange {
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        let day = (date.month(), date.day());
+        if self.from <= self.to {
+            self.from <= day && day <= self.to
+        } else {
+            day >= self.from || day <= self.to
+        }
+    }
+}
+
+// ----------------------------------------------------------------------
+// TimeOfDay Enum
+// ----------------------------------------------------------------------
+/// A window boundary: a fixed local time, or an offset from the day's sunrise or sunset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeOfDay {
+    Fixed(NaiveTime),
+    Sun { event: SunEvent, offset: TimeDelta },
+}
+
+impl TimeOfDay {
+    /// Parses `HH:MM`, `HH:MM:SS`, or `sunrise`/`sunset` with an optional offset such as
+    /// `sunset + 15min` or `sunrise-1h`.
+    pub fn parse(value: &str) -> Result<Self> {
+        let trimmed = value.trim();
+        let lower = trimmed.to_ascii_lowercase();
+        let (event, rest) = if let Some(rest) = lower.strip_prefix("sunrise") {
+            (SunEvent::Sunrise, rest)
+        } else if let Some(rest) = lower.strip_prefix("sunset") {
+            (SunEvent::Sunset, rest)
+        } else {
+            return parse_time_of_day(trimmed).map(TimeOfDay::Fixed);
+        };
+
+        let rest = rest.trim_start();
+        let offset = if rest.is_empty() {
+            TimeDelta::zero()
+        } else {
+            let (sign, duration) = match rest.split_at(1) {
+                ("+", duration) => (1, duration),
+                ("-", duration) => (-1, duration),
+                _ => bail!("invalid time {:?} (expected e.g. sunset + 15min)", value),
+            };
+            let duration = TimeDelta::from_std(parse_gap(duration)?)
+                .with_context(|| format!("offset in {:?} is too large", value))?;
+            duration * sign
+        };
+        Ok(TimeOfDay::Sun { event, offset })
+    }
+
+    pub fn is_sun_relative(&self) -> bool {
+        matches!(self, TimeOfDay::Sun { .. })
+    }
+
+    /// The local time this boundary falls at on `day`, or `None` if the sun does not rise or
+    /// set that day.
+    pub fn on<Tz: TimeZone>(&self, day: NaiveDate, location: Option<&Location>, tz: &Tz) -> Option<NaiveDateTime> {
+        match *self {
+            TimeOfDay::Fixed(time) => Some(day.and_time(time)),
+            TimeOfDay::Sun { event, offset } => {
+                let utc = location?.sun_event_utc(day, event)?;
+                Some(tz.from_utc_datetime(&utc).naive_local() + offset)
+            }
+        }
+    }
+
+    /// Clock time used to decide whether a window runs past midnight, counting sunrise as
+    /// 06:00 and sunset as 18:00.
+    fn nominal(&self) -> NaiveTime {
+        match *self {
+            TimeOfDay::Fixed(time) => time,
+            TimeOfDay::Sun { event, offset } => {
+                let hour = if event == SunEvent::Sunrise { 6 } else { 18 };
+                NaiveTime::from_hms_opt(hour, 0, 0).expect("valid time").overflowing_add_signed(offset).0
+            }
+        }
+    }
+}
+
+// ----------------------------------------------------------------------
+// ScheduleWindow Struct
+// ----------------------------------------------------------------------
+/// A daily time window, limited to some weekdays and dates, with the playlist to run in it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduleWindow {
+    pub name: String,
+    /// Weekdays the window opens on; empty means every day.
+    pub days: Vec<Weekday>,
+    /// Dates the window opens on; `None` means all year.
+    pub dates: Option<DateRange>,
+    pub start: TimeOfDay,
+    /// End of the window; at or before `start` means it closes the next day. Sunrise counts as
+    /// morning and sunset as evening when deciding that.
+    pub end: TimeOfDay,
+    /// Playlist for the window; `None` plays the player's default playlist.
+    pub playlist: Option<Playlist>,
+}
+
+impl ScheduleWindow {
+    /// Whether the window opens on `day`. A window that runs past midnight belongs to the day it opens.
+    pub fn opens_on(&self, day: NaiveDate) -> bool {
+        (self.days.is_empty() || self.days.contains(&day.weekday()))
+            && self.dates.is_none_or(|dates| dates.contains(day))
+    }
+
+    /// The local start and end of the window opening on `day`. Whether the end falls on the
+    /// next day is decided by the nominal times (sunrise 06:00, sunset 18:00), so `sunset`
+    /// until `17:00` runs overnight until 17:00 the next day. `None` if a sun-relative boundary
+    /// does not exist that day, or falls so that the window would be empty, such as `sunset`
+    /// until `19:00` when the sun sets after 19:00.
+    pub fn interval_on<Tz: TimeZone>(&self, day: NaiveDate, location: Option<&Location>, tz: &Tz) -> Option<(NaiveDateTime, NaiveDateTime)> {
+        let end_day = if self.end.nominal() <= self.start.nominal() { day.succ_opt()? } else { day };
+        let start = self.start.on(day, location, tz)?;
+        let end = self.end.on(end_day, location, tz)?;
+        (start < end).then_some((start, end))
+    }
+
+    pub fn contains<Tz: TimeZone>(&self, now: &DateTime<Tz>, location: Option<&Location>) -> bool {
+        let local = now.naive_local();
+        // Only windows opening today or, past midnight, yesterday can contain `now`.
+        [Some(local.date()), local.date().pred_opt()].into_iter().flatten().any(|day| {
+            self.opens_on(day)
+                && self
+                    .interval_on(day, location, &now.timezone())
+                    .is_some_and(|(start, end)| start <= local && local < end)
+        })
+    }
+
+    pub fn is_sun_relative(&self) -> bool {
+        self.start.is_sun_relative() || self.end.is_sun_relative()
+    }
+}
+
+// ----------------------------------------------------------------------
+// Schedule Struct
+// ----------------------------------------------------------------------
+/// When the show runs. Windows are checked in order and the first one containing the
+/// current local time is active.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Schedule {
+    pub windows: Vec<ScheduleWindow>,
+    pub outside: OutsideBehavior,
+    /// Where sunrise and sunset are computed for; required by sun-relative windows.
+    pub location: Option<Location>,
+}
+
+impl Schedule {
+    /// Loads a JSON schedule. Relative playlist paths are resolved against the schedule's directory.
+    pub fn load(path: &Path) -> Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read schedule {}", path.display()))?;
+        let base_dir = path.parent().unwrap_or_else(|| Path::new(""));
+        Self::parse_json(&text, base_dir).with_context(|| format!("Invalid schedule {}", path.display()))
+    }
+
+    /// Parses a JSON schedule:
+    ///
+    /// ```json
+    /// { "outside": "blank",
+    ///   "location": { "latitude": 40.71, "longitude": -74.01 },
+    ///   "windows": [{ "name": "Weekends", "days": ["fri", "sat"], "dates": { "from": "11-27", "to": "01-06" },
+    ///                 "start": "sunset + 15min", "end": "23:30", "playlist": "weekend.m3u" }] }
+    /// ```
+    ///
+    /// `playlist` is an M3U/JSON playlist path or an array of sequence paths. `location` is
+    /// only needed when a window starts or ends relative to sunrise or sunset.
+    pub fn parse_json(text: &str, base_dir: &Path) -> Result<Self> {
+        let document: JsonSchedule = serde_json::from_str(text)?;
+        if document.windows.is_empty() {
+            bail!("Schedule has no windows");
+        }
+
+        let windows = document
+            .windows
+            .into_iter()
+            .enumerate()
+            .map(|(index, window)| {
+                let name = window.name.clone().unwrap_or_else(|| format!("window {}", index + 1));
+                window.into_window(name.clone(), base_dir).with_context(|| format!("Invalid schedule window {:?}", name))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        match &document.location {
+            Some(location) => location.validate().context("Invalid schedule location")?,
+            None => {
+                if let Some(window) = windows.iter().find(|window| window.is_sun_relative()) {
+                    bail!("Window {:?} is relative to sunrise or sunset but the schedule has no location", window.name);
+                }
+            }
+        }
+
+        Ok(Schedule { windows, outside: document.outside, location: document.location })
+    }
+
+    /// Index of the first window containing `now`.
+    pub fn active_window<Tz: TimeZone>(&self, now: &DateTime<Tz>) -> Option<usize> {
+        self.windows.iter().position(|window| window.contains(now, self.location.as_ref()))
+    }
+}
+
+/// Parses `HH:MM` or `HH:MM:SS`.
+pub fn parse_time_of_day(value: &str) -> Result<NaiveTime> {
+    NaiveTime::parse_from_str(value, "%H:%M")
+        .or_else(|_| NaiveTime::parse_from_str(value, "%H:%M:%S"))
+        .with_context(|| format!("invalid time of day {:?} (expected HH:MM)", value))
+}
+
+/// Parses a yearly `MM-DD` date.
+fn parse_month_day(value: &str) -> Result<(u32, u32)> {
+    let parsed = value.split_once('-').and_then(|(month, day)| Some((month.parse().ok()?, day.parse().ok()?)));
+    match parsed {
+        // 2000 is a leap year, so February 29 is accepted.
+        Some((month, day)) if NaiveDate::from_ymd_opt(2000, month, day).is_some() => Ok((month, day)),
+        _ => bail!("invalid date {:?} (expected MM-DD)", value),
+    }
+}
+
+#[derive(Deserialize)]
+struct JsonSchedule {
+    #[serde(default)]
+    outside: OutsideBehavior,
+    location: Option<Location>,
+    windows: Vec<JsonWindow>,
+}
+
+#[derive(Deserialize)]
+struct JsonWindow {
+    name: Option<String>,
+    #[serde(default)]
+    days: Vec<String>,
+    dates: Option<JsonDateRange>,
+    start: String,
+    end: String,
+    playlist: Option<JsonPlaylistRef>,
+}
+
+impl JsonWindow {
+    fn into_window(self, name: String, base_dir: &Path) -> Result<ScheduleWindow> {
+        let days = self
+            .days
+            .iter()
+            .map(|day| day.parse::<Weekday>().map_err(|_| anyhow::anyhow!("invalid weekday {:?}", day)))
+            .collect::<Result<Vec<_>>>()?;
+
+        let dates = self
+            .dates
+            .map(|range| -> Result<DateRange> { Ok(DateRange { from: parse_month_day(&range.from)?, to: parse_month_day(&range.to)? }) })
+            .transpose()?;
+
+        let playlist = match self.playlist {
+            None => None,
+            Some(JsonPlaylistRef::Path(path)) => Some(Playlist::load(&base_dir.join(path))?),
+            Some(JsonPlaylistRef::Files(files)) => {
+                let playlist = Playlist::from_files(files.into_iter().map(|file| base_dir.join(file)));
+                playlist.validate()?;
+                Some(playlist)
+            }
+        };
+
+        Ok(ScheduleWindow {
+            name,
+            days,
+            dates,
+            start: TimeOfDay::parse(&self.start)?,
+            end: TimeOfDay::parse(&self.end)?,
+            playlist,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct JsonDateRange {
+    from: String,
+    to: String,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JsonPlaylistRef {
+    Path(PathBuf),
+    Files(Vec<PathBuf>),
+}
+
+// ----------------------------------------------------------------------
+// Scheduler Task
+// ----------------------------------------------------------------------
+/// Publishes the active window index (or `None` outside all windows) on `tx_active_window`
+/// whenever it changes, checking the local clock every second.
+pub fn run_scheduler(schedule: Schedule, tx_active_window: Sender<Option<usize>>) {
+    tokio::spawn(async move {
+        log::info!("Scheduler started with {} windows.", schedule.windows.len());
+
+        let mut announced = false;
+        let mut sun_logged_for = None;
+        loop {
+            let now = Local::now();
+            if let Some(location) = &schedule.location {
+                let today = now.date_naive();
+                if sun_logged_for != Some(today) {
+                    sun_logged_for = Some(today);
+                    log_sun_times(location, today);
+                }
+            }
+
+            let active = schedule.active_window(&now);
+            if !announced || *tx_active_window.borrow() != active {
+                announced = true;
+                match active {
+                    Some(index) => log::info!("Scheduler: Window {:?} is open.", schedule.windows[index].name),
+                    None => log::info!("Scheduler: Outside all windows ({:?}).", schedule.outside),
+                }
+                if tx_active_window.send(active).is_err() {
+                    return;
+                }
+            }
+            tokio::time::sleep(SCHEDULE_POLL_INTERVAL).await;
+        }
+    });
+}
+
+fn log_sun_times(location: &Location, day: NaiveDate) {
+    let local = |event| {
+        location
+            .sun_event_utc(day, event)
+            .map_or_else(|| "none".to_string(), |utc| Local.from_utc_datetime(&utc).format("%H:%M").to_string())
+    };
+    log::info!("Scheduler: Sunrise {}, sunset {} on {}.", local(SunEvent::Sunrise), local(SunEvent::Sunset), day);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{FixedOffset, Utc};
+
+    fn at(date: (i32, u32, u32), time: &str) -> DateTime<Utc> {
+        NaiveDate::from_ymd_opt(date.0, date.1, date.2).unwrap().and_time(parse_time_of_day(time).unwrap()).and_utc()
+    }
+
+    fn window(days: Vec<Weekday>, start: &str, end: &str) -> ScheduleWindow {
+        ScheduleWindow {
+            name: "test".to_string(),
+            days,
+            dates: None,
+            start: TimeOfDay::parse(start).unwrap(),
+            end: TimeOfDay::parse(end).unwrap(),
+            playlist: None,
+        }
+    }
+
+    #[test]
+    fn matches_weekly_and_overnight_windows() {
+        // 2025-12-05 is a Friday.
+        let weekend = window(vec![Weekday::Fri, Weekday::Sat], "17:00", "23:30");
+        assert!(weekend.contains(&at((2025, 12, 5), "17:00"), None));
+        assert!(!weekend.contains(&at((2025, 12, 5), "23:30"), None));
+        assert!(!weekend.contains(&at((2025, 12, 4), "18:00"), None));
+
+        // A Saturday window running past midnight still applies early on Sunday, but not Monday.
+        let late = window(vec![Weekday::Sat], "22:00", "01:00");
+        assert!(late.contains(&at((2025, 12, 6), "23:00"), None));
+        assert!(late.contains(&at((2025, 12, 7), "00:59"), None));
+        assert!(!late.contains(&at((2025, 12, 7), "22:30"), None));
+        assert!(!late.contains(&at((2025, 12, 8), "00:30"), None));
+    }
+
+    #[test]
+    fn parses_sun_relative_times() {
+        assert_eq!(TimeOfDay::parse("sunset").unwrap(), TimeOfDay::Sun { event: SunEvent::Sunset, offset: TimeDelta::zero() });
+        assert_eq!(
+            TimeOfDay::parse("Sunset + 15min").unwrap(),
+            TimeOfDay::Sun { event: SunEvent::Sunset, offset: TimeDelta::minutes(15) }
+        );
+        assert_eq!(
+            TimeOfDay::parse("sunrise-1h 30m").unwrap(),
+            TimeOfDay::Sun { event: SunEvent::Sunrise, offset: TimeDelta::minutes(-90) }
+        );
+        assert_eq!(TimeOfDay::parse("22:30").unwrap(), TimeOfDay::Fixed(NaiveTime::from_hms_opt(22, 30, 0).unwrap()));
+        assert!(TimeOfDay::parse("sunset 15min").is_err());
+        assert!(TimeOfDay::parse("sundown").is_err());
+    }
+
+    #[test]
+    fn sun_relative_windows_follow_local_sunset() {
+        // New York (UTC-5 in December) sets at about 16:29 local time on 2025-12-01.
+        let new_york = Location { latitude: 40.7128, longitude: -74.0060 };
+        let est = FixedOffset::west_opt(5 * 3600).unwrap();
+        let local = |time: &str| est.from_local_datetime(&at((2025, 12, 1), time).naive_utc()).unwrap();
+
+        let evening = window(vec![], "sunset + 15min", "22:30");
+        assert!(!evening.contains(&local("16:40"), Some(&new_york)));
+        assert!(evening.contains(&local("16:50"), Some(&new_york)));
+        assert!(!evening.contains(&local("22:30"), Some(&new_york)));
+        // Without a location the sun never sets, so the window never opens.
+        assert!(!evening.contains(&local("18:00"), None));
+
+        // Sunset until sunrise runs overnight, ending at the next morning's sunrise (about 07:01).
+        let night = window(vec![Weekday::Mon], "sunset", "sunrise");
+        assert!(night.contains(&local("23:00"), Some(&new_york)));
+        let tuesday = |time: &str| est.from_local_datetime(&at((2025, 12, 2), time).naive_utc()).unwrap();
+        assert!(night.contains(&tuesday("06:55"), Some(&new_york)));
+        assert!(!night.contains(&tuesday("07:10"), Some(&new_york)));
+    }
+
+    #[test]
+    fn sun_relative_ends_use_nominal_times() {
+        // London sets at about 20:21 UTC on 2025-06-21.
+        let london = Location { latitude: 51.5074, longitude: -0.1278 };
+        let midsummer = NaiveDate::from_ymd_opt(2025, 6, 21).unwrap();
+
+        // 19:00 is after the nominal 18:00 sunset, so the window would close before it opens.
+        assert_eq!(window(vec![], "sunset", "19:00").interval_on(midsummer, Some(&london), &Utc), None);
+
+        // 17:00 is before it, so the window closes at 17:00 the next day.
+        let (start, end) = window(vec![], "sunset", "17:00").interval_on(midsummer, Some(&london), &Utc).unwrap();
+        assert_eq!(start.date(), midsummer);
+        assert_eq!(end, at((2025, 6, 22), "17:00").naive_utc());
+    }
+
+    #[test]
+    fn date_ranges_wrap_around_the_new_year() {
+        let season = DateRange { from: (11, 27), to: (1, 6) };
+        assert!(season.contains(NaiveDate::from_ymd_opt(2025, 11, 27).unwrap()));
+        assert!(season.contains(NaiveDate::from_ymd_opt(2025, 12, 31).unwrap()));
+        assert!(season.contains(NaiveDate::from_ymd_opt(2026, 1, 6).unwrap()));
+        assert!(!season.contains(NaiveDate::from_ymd_opt(2026, 1, 7).unwrap()));
+        assert!(!season.contains(NaiveDate::from_ymd_opt(2025, 11, 26).unwrap()));
+
+        let july = DateRange { from: (7, 1), to: (7, 31) };
+        assert!(july.contains(NaiveDate::from_ymd_opt(2025, 7, 4).unwrap()));
+        assert!(!july.contains(NaiveDate::from_ymd_opt(2025, 8, 1).unwrap()));
+    }
+
+    #[test]
+    fn parses_json_schedules() {
+        let text = r#"{
+            "outside": "idle",
+            "windows": [
+                { "name": "Weekends", "days": ["fri", "Saturday"], "dates": { "from": "11-27", "to": "01-06" },
+                  "start": "17:00", "end": "23:30", "playlist": ["a.fseq", "b.fseq"] },
+                { "start": "17:30", "end": "22:00" }
+            ]
+        }"#;
+        let schedule = Schedule::parse_json(text, Path::new("/show")).unwrap();
+
+        assert_eq!(schedule.outside, OutsideBehavior::Idle);
+        assert_eq!(schedule.windows[0].days, vec![Weekday::Fri, Weekday::Sat]);
+        assert_eq!(schedule.windows[0].playlist, Some(Playlist::from_files(["/show/a.fseq", "/show/b.fseq"])));
+        assert_eq!(schedule.windows[1].name, "window 2");
+        assert_eq!(schedule.windows[1].playlist, None);
+
+        // Friday in season matches the first window; a Wednesday falls through to the second.
+        assert_eq!(schedule.active_window(&at((2025, 12, 5), "18:00")), Some(0));
+        assert_eq!(schedule.active_window(&at((2025, 12, 3), "18:00")), Some(1));
+        assert_eq!(schedule.active_window(&at((2025, 12, 3), "12:00")), None);
+    }
+
+    #[test]
+    fn rejects_invalid_schedules() {
+        let path = Path::new("");
+        assert!(Schedule::parse_json(r#"{ "windows": [] }"#, path).is_err());
+        assert!(Schedule::parse_json(r#"{ "windows": [{ "start": "25:00", "end": "22:00" }] }"#, path).is_err());
+        assert!(Schedule::parse_json(r#"{ "windows": [{ "days": ["someday"], "start": "17:00", "end": "22:00" }] }"#, path).is_err());
+        assert!(Schedule::parse_json(r#"{ "windows": [{ "dates": { "from": "02-30", "to": "03-01" }, "start": "17:00", "end": "22:00" }] }"#, path).is_err());
+        // Sun-relative windows need a valid location.
+        assert!(Schedule::parse_json(r#"{ "windows": [{ "start": "sunset", "end": "22:00" }] }"#, path).is_err());
+        assert!(Schedule::parse_json(r#"{ "location": { "latitude": 95, "longitude": 0 }, "windows": [{ "start": "17:00", "end": "22:00" }] }"#, path).is_err());
+        assert!(Schedule::parse_json(r#"{ "location": { "latitude": 40.7, "longitude": -74 }, "windows": [{ "start": "sunset", "end": "22:00" }] }"#, path).is_ok());
+    }
+}
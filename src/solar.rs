@@ -0,0 +1,119 @@
+// src/solar.rs
+use anyhow::{Result, bail};
+use chrono::{NaiveDate, NaiveDateTime, TimeDelta};
+use serde::Deserialize;
+
+/// Solar altitude at sunrise and sunset: the sun's upper limb on the horizon, corrected for
+/// atmospheric refraction.
+const HORIZON_ALTITUDE_DEG: f64 = -0.833;
+
+/// Obliquity of the ecliptic.
+const EARTH_TILT_DEG: f64 = 23.4397;
+
+// ----------------------------------------------------------------------
+// SunEvent Enum
+// ----------------------------------------------------------------------
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SunEvent {
+    Sunrise,
+    Sunset,
+}
+
+// ----------------------------------------------------------------------
+// Location Struct
+// ----------------------------------------------------------------------
+/// Where the show is, for computing sunrise and sunset offline.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct Location {
+    /// Degrees north; south is negative.
+    pub latitude: f64,
+    /// Degrees east; west is negative.
+    pub longitude: f64,
+}
+
+impl Location {
+    pub fn validate(&self) -> Result<()> {
+        if !(-90.0..=90.0).contains(&self.latitude) {
+            bail!("latitude {} is outside -90..90", self.latitude);
+        }
+        if !(-180.0..=180.0).contains(&self.longitude) {
+            bail!("longitude {} is outside -180..180", self.longitude);
+        }
+        Ok(())
+    }
+
+    /// UTC time of `event` on the local calendar `date`, using the sunrise equation
+    /// (accurate to about a minute at mid latitudes). `None` when the sun does not rise or
+    /// set that day, as in a polar night or midnight sun.
+    pub fn sun_event_utc(&self, date: NaiveDate, event: SunEvent) -> Option<NaiveDateTime> {
+        let days_since_j2000 = (date - j2000_date()).num_days() as f64;
+
+        // Mean solar noon at this longitude, in days since J2000.0.
+        let mean_noon = days_since_j2000 - self.longitude / 360.0;
+        let mean_anomaly = (357.5291 + 0.98560028 * mean_noon).rem_euclid(360.0).to_radians();
+        let center = 1.9148 * mean_anomaly.sin() + 0.0200 * (2.0 * mean_anomaly).sin() + 0.0003 * (3.0 * mean_anomaly).sin();
+        let ecliptic_longitude = (mean_anomaly.to_degrees() + center + 180.0 + 102.9372).rem_euclid(360.0).to_radians();
+        let transit = mean_noon + 0.0053 * mean_anomaly.sin() - 0.0069 * (2.0 * ecliptic_longitude).sin();
+
+        let declination = (ecliptic_longitude.sin() * EARTH_TILT_DEG.to_radians().sin()).asin();
+        let latitude = self.latitude.to_radians();
+        let cos_hour_angle = (HORIZON_ALTITUDE_DEG.to_radians().sin() - latitude.sin() * declination.sin())
+            / (latitude.cos() * declination.cos());
+        if !(-1.0..=1.0).contains(&cos_hour_angle) {
+            return None;
+        }
+
+        let half_day = cos_hour_angle.acos().to_degrees() / 360.0;
+        let days = match event {
+            SunEvent::Sunrise => transit - half_day,
+            SunEvent::Sunset => transit + half_day,
+        };
+        Some(j2000_date().and_hms_opt(12, 0, 0)? + TimeDelta::milliseconds((days * 86_400_000.0).round() as i64))
+    }
+}
+
+/// The J2000.0 epoch's date; the equation counts days from its noon.
+fn j2000_date() -> NaiveDate {
+    NaiveDate::from_ymd_opt(2000, 1, 1).expect("valid date")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(actual: Option<NaiveDateTime>, expected: &str) {
+        let expected = NaiveDateTime::parse_from_str(expected, "%Y-%m-%d %H:%M").unwrap();
+        let actual = actual.expect("sun event exists");
+        assert!((actual - expected).num_seconds().abs() <= 180, "expected about {}, got {}", expected, actual);
+    }
+
+    #[test]
+    fn computes_sunrise_and_sunset() {
+        // Published times, in UTC.
+        let london = Location { latitude: 51.5074, longitude: -0.1278 };
+        let midsummer = NaiveDate::from_ymd_opt(2025, 6, 21).unwrap();
+        assert_near(london.sun_event_utc(midsummer, SunEvent::Sunrise), "2025-06-21 03:43");
+        assert_near(london.sun_event_utc(midsummer, SunEvent::Sunset), "2025-06-21 20:21");
+
+        // A Los Angeles sunset falls after midnight UTC but still belongs to the local date.
+        let los_angeles = Location { latitude: 34.0522, longitude: -118.2437 };
+        let december = NaiveDate::from_ymd_opt(2025, 12, 1).unwrap();
+        assert_near(los_angeles.sun_event_utc(december, SunEvent::Sunrise), "2025-12-01 14:39");
+        assert_near(los_angeles.sun_event_utc(december, SunEvent::Sunset), "2025-12-02 00:44");
+    }
+
+    #[test]
+    fn no_events_in_polar_night() {
+        let tromso = Location { latitude: 69.6492, longitude: 18.9553 };
+        let december = NaiveDate::from_ymd_opt(2025, 12, 21).unwrap();
+        assert_eq!(tromso.sun_event_utc(december, SunEvent::Sunrise), None);
+        assert_eq!(tromso.sun_event_utc(december, SunEvent::Sunset), None);
+    }
+
+    #[test]
+    fn rejects_out_of_range_coordinates() {
+        assert!(Location { latitude: 91.0, longitude: 0.0 }.validate().is_err());
+        assert!(Location { latitude: 0.0, longitude: -181.0 }.validate().is_err());
+        assert!(Location { latitude: -33.9, longitude: 151.2 }.validate().is_ok());
+    }
+}
@@ -1,4 +1,161 @@
-// src/test_data.rs (New File)
+// src/test_data.rs
 
-// This module can contain mock FSEQ data or utility functions for testing.
-// It is empty for now to satisfy the compiler.
+// Mock FSEQ data for tests: files covering the layouts xLights and FPP write, built with FseqWriter.
+
+use crate::fseq::{ChannelRange, CompressionType, VariableHeader};
+use crate::fseq_writer::{FseqVersion, FseqWriter};
+
+pub const STEP_TIME_MS: u8 = 25;
+pub const MEDIA_FILENAME: &str = "song.mp3";
+pub const SEQUENCE_PRODUCER: &str = "xLights 2024.01";
+
+/// Deterministic channel value so every frame of every fixture can be verified.
+pub fn frame_value(frame: u32, channel: u32) -> u8 {
+    (frame.wrapping_mul(7).wrapping_add(channel.wrapping_mul(13)) & 0xFF) as u8
+}
+
+pub fn frame_data(frame: u32, channels: u32) -> Vec<u8> {
+    (0..channels).map(|channel| frame_value(frame, channel)).collect()
+}
+
+/// Layout options for a v2 fixture.
+pub struct V2Fixture {
+    pub compression: CompressionType,
+    pub frames: u32,
+    pub channels: u32,
+    /// Frames per compression block; 0 writes a single compressed stream without a block table.
+    pub frames_per_block: u32,
+    /// Writes a 10-frame first block, as xLights does for fast playback start.
+    pub small_first_block: bool,
+    /// Unused zero-sized block table entries, as FPP pads its tables.
+    pub padding_blocks: usize,
+    /// Sparse (start channel, channel count) ranges; their counts must add up to `channels`.
+    pub sparse_ranges: Vec<(u32, u32)>,
+}
+
+impl V2Fixture {
+    pub fn new(compression: CompressionType, frames: u32, channels: u32, frames_per_block: u32) -> Self {
+        V2Fixture {
+            compression,
+            frames,
+            channels,
+            frames_per_block,
+            small_first_block: false,
+            padding_blocks: 0,
+            sparse_ranges: Vec::new(),
+        }
+    }
+
+    pub fn build(&self) -> Vec<u8> {
+        let mut writer = FseqWriter::new(self.channels, STEP_TIME_MS)
+            .with_compression(self.compression)
+            .with_frames_per_block(self.frames_per_block)
+            .with_sequence_uid(0x1234_5678)
+            .with_variable_header(VariableHeader::MediaFilename(MEDIA_FILENAME.to_string()))
+            .with_variable_header(VariableHeader::SequenceProducer(SEQUENCE_PRODUCER.to_string()))
+            .with_variable_header(VariableHeader::Unknown { code: *b"zz", data: vec![1, 2, 3] })
+            .with_channel_ranges(self.sparse_ranges.iter()
+                .map(|&(start_channel, channel_count)| ChannelRange { start_channel, channel_count })
+                .collect());
+
+        if self.small_first_block {
+            writer = writer.with_first_block_frames(10);
+        }
+
+        for frame in 0..self.frames {
+            writer.append_frame(&frame_data(frame, self.channels)).unwrap();
+        }
+
+        let out = writer.to_bytes().unwrap();
+        if self.padding_blocks > 0 {
+            pad_block_table(out, self.padding_blocks)
+        } else {
+            out
+        }
+    }
+}
+
+/// Appends unused zero-sized entries to the block table and shifts the header offsets past them.
+fn pad_block_table(mut out: Vec<u8>, padding_blocks: usize) -> Vec<u8> {
+    let block_count = (((out[20] & 0xF0) as usize) << 4) | out[21] as usize;
+    let table_end = 32 + block_count * 8;
+    out.splice(table_end..table_end, std::iter::repeat_n(0u8, padding_blocks * 8));
+
+    let padded_count = block_count + padding_blocks;
+    out[20] = (out[20] & 0x0F) | ((((padded_count >> 8) & 0x0F) as u8) << 4);
+    out[21] = (padded_count & 0xFF) as u8;
+
+    for offset_field in [4, 8] {
+        let offset = u16::from_le_bytes([out[offset_field], out[offset_field + 1]]) as usize + padding_blocks * 8;
+        out[offset_field..offset_field + 2].copy_from_slice(&(offset as u16).to_le_bytes());
+    }
+
+    out
+}
+
+/// Builds an uncompressed FSEQ v1 file with an `mf` variable header.
+/// Older writers used the `FSEQ` magic instead of `PSEQ`, so the magic is patched in.
+pub fn build_v1(frames: u32, channels: u32, magic: &[u8; 4]) -> Vec<u8> {
+    let mut writer = FseqWriter::new(channels, STEP_TIME_MS)
+        .with_version(FseqVersion::V1)
+        .with_variable_header(VariableHeader::MediaFilename(MEDIA_FILENAME.to_string()));
+
+    for frame in 0..frames {
+        writer.append_frame(&frame_data(frame, channels)).unwrap();
+    }
+
+    let mut out = writer.to_bytes().unwrap();
+    out[..4].copy_from_slice(magic);
+    out
+}
+
+/// Every v2 layout the parsers must agree on.
+pub fn v2_fixtures() -> Vec<(&'static str, Vec<u8>)> {
+    let mut zstd_first_block = V2Fixture::new(CompressionType::Zstd, 600, 50, 256);
+    zstd_first_block.small_first_block = true;
+
+    let mut zstd_padded = V2Fixture::new(CompressionType::Zstd, 600, 50, 64);
+    zstd_padded.padding_blocks = 5;
+
+    let mut zlib_sparse = V2Fixture::new(CompressionType::Zlib, 300, 40, 100);
+    zlib_sparse.sparse_ranges = vec![(100, 20), (1000, 20)];
+
+    let mut uncompressed_sparse = V2Fixture::new(CompressionType::None, 120, 30, 0);
+    uncompressed_sparse.sparse_ranges = vec![(3, 12), (600, 18)];
+
+    vec![
+        ("uncompressed", V2Fixture::new(CompressionType::None, 300, 50, 0).build()),
+        ("uncompressed_sparse", uncompressed_sparse.build()),
+        ("zstd_small_first_block", zstd_first_block.build()),
+        ("zstd_padded_table", zstd_padded.build()),
+        ("zstd_single_stream", V2Fixture::new(CompressionType::Zstd, 300, 50, 0).build()),
+        ("zlib", V2Fixture::new(CompressionType::Zlib, 600, 50, 256).build()),
+        ("zlib_sparse", zlib_sparse.build()),
+        ("zlib_single_stream", V2Fixture::new(CompressionType::Zlib, 300, 50, 0).build()),
+    ]
+}
+
+/// A scratch directory under the system temp dir, removed when dropped.
+pub struct TempDir(std::path::PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("wled-sequencer-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+
+    pub fn path(&self) -> &std::path::Path {
+        &self.0
+    }
+
+    pub fn file(&self, name: &str) -> String {
+        self.0.join(name).to_string_lossy().into_owned()
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}